@@ -0,0 +1,108 @@
+use std::fmt;
+
+use crate::position::{square_name, Piece};
+
+/// A fully described move: where a piece goes from and to, plus what kind of move it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChessMove {
+    /// Where the piece stood, or for a drop the square it is dropped on.
+    pub from: [usize; 2],
+    pub to: [usize; 2],
+    /// The piece being moved.
+    pub piece: Piece,
+    pub promotion: Option<Piece>,
+    pub capture: bool,
+    pub castle: bool,
+    pub en_passant: bool,
+    pub double_push: bool,
+    /// Whether the piece comes from the mover's pocket, in Crazyhouse.
+    pub drop: bool,
+}
+
+impl ChessMove {
+    pub fn new(from: [usize; 2], to: [usize; 2], piece: Piece) -> Self {
+        Self {
+            from,
+            to,
+            piece,
+            promotion: None,
+            capture: false,
+            castle: false,
+            en_passant: false,
+            double_push: false,
+            drop: false,
+        }
+    }
+
+    /// Dropping `piece` from the pocket onto `to`.
+    pub fn drop(piece: Piece, to: [usize; 2]) -> Self {
+        Self {
+            drop: true,
+            ..Self::new(to, to, piece)
+        }
+    }
+
+    /// Long algebraic notation as used by UCI, e.g. `e2e4`, `e7e8q` or the drop `N@f3`.
+    pub fn to_uci(&self) -> String {
+        if self.drop {
+            return format!(
+                "{}@{}",
+                piece_letter(self.piece),
+                square_name(self.to[0], self.to[1])
+            );
+        }
+        let mut uci = format!(
+            "{}{}",
+            square_name(self.from[0], self.from[1]),
+            square_name(self.to[0], self.to[1])
+        );
+        if let Some(piece) = self.promotion {
+            uci.push(piece_letter(piece).to_ascii_lowercase());
+        }
+        uci
+    }
+}
+
+impl fmt::Display for ChessMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_uci())
+    }
+}
+
+/// The upper case English letter for a piece, with pawns as `P`.
+pub fn piece_letter(piece: Piece) -> char {
+    match piece {
+        Piece::King => 'K',
+        Piece::Queen => 'Q',
+        Piece::Rook => 'R',
+        Piece::Bishop => 'B',
+        Piece::Knight => 'N',
+        Piece::Pawn => 'P',
+        Piece::Empty => ' ',
+    }
+}
+
+/// The piece for an English piece letter in either case.
+pub fn piece_from_letter(c: char) -> Option<Piece> {
+    match c.to_ascii_lowercase() {
+        'k' => Some(Piece::King),
+        'q' => Some(Piece::Queen),
+        'r' => Some(Piece::Rook),
+        'b' => Some(Piece::Bishop),
+        'n' => Some(Piece::Knight),
+        'p' => Some(Piece::Pawn),
+        _ => None,
+    }
+}
+
+/// Parses an algebraic square name such as `e4` into `[x, y]`.
+pub fn parse_square(s: &str) -> Option<[usize; 2]> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    if !(b'a'..=b'h').contains(&bytes[0]) || !(b'1'..=b'8').contains(&bytes[1]) {
+        return None;
+    }
+    Some([(bytes[0] - b'a') as usize, (b'8' - bytes[1]) as usize])
+}
@@ -0,0 +1,167 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Moves a game is assumed to still last when planning how long to think.
+const MOVES_TO_GO: u64 = 30;
+/// Time kept back when thinking on a clock, so the move arrives before the flag falls.
+const TIME_MARGIN: Duration = Duration::from_millis(50);
+
+/// How the extra seconds of a time control are given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Increment {
+    /// Added to the clock after every move.
+    Fischer,
+    /// Added back after every move, but never more than the move took.
+    Bronstein,
+    /// The clock only starts running once this much of each move has passed.
+    Delay,
+}
+
+/// A time control such as `5+3`: minutes for the game plus seconds per move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+    pub method: Increment,
+}
+
+/// Why a time control could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeControlError(pub String);
+
+impl fmt::Display for TimeControlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is not a time control, expected minutes and seconds per move such as 5+3",
+            self.0
+        )
+    }
+}
+
+impl Error for TimeControlError {}
+
+impl FromStr for TimeControl {
+    type Err = TimeControlError;
+
+    /// Parses `<minutes>+<seconds>` or just `<minutes>`, with a Fischer increment.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || TimeControlError(s.to_string());
+        let (minutes, seconds) = s.split_once('+').unwrap_or((s, "0"));
+        let minutes: f64 = minutes.trim().parse().map_err(|_| error())?;
+        let seconds: f64 = seconds.trim().parse().map_err(|_| error())?;
+        if !minutes.is_finite() || !seconds.is_finite() || minutes <= 0.0 || seconds < 0.0 {
+            return Err(error());
+        }
+
+        Ok(Self {
+            base: Duration::try_from_secs_f64(minutes * 60.0).map_err(|_| error())?,
+            increment: Duration::try_from_secs_f64(seconds).map_err(|_| error())?,
+            method: Increment::Fischer,
+        })
+    }
+}
+
+impl TimeControl {
+    /// The value of the PGN `TimeControl` tag, e.g. `300+3`.
+    pub fn pgn_tag(&self) -> String {
+        if self.increment.is_zero() {
+            self.base.as_secs().to_string()
+        } else {
+            format!("{}+{}", self.base.as_secs(), self.increment.as_secs())
+        }
+    }
+}
+
+/// The time each side has left. Clocks are stopped between calls: callers measure how long
+/// a move took and pass it in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clock {
+    pub control: TimeControl,
+    remaining: [Duration; 2],
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            remaining: [control.base; 2],
+        }
+    }
+
+    /// Time left for `color` before its current move.
+    pub fn remaining(&self, color: usize) -> Duration {
+        self.remaining[color]
+    }
+
+    pub fn set_remaining(&mut self, color: usize, remaining: Duration) {
+        self.remaining[color] = remaining;
+    }
+
+    /// Time left for `color` once it has been thinking for `thinking`, as its running clock
+    /// shows it. Zero means its flag has fallen.
+    pub fn running(&self, color: usize, thinking: Duration) -> Duration {
+        let used = match self.control.method {
+            Increment::Delay => thinking.saturating_sub(self.control.increment),
+            Increment::Fischer | Increment::Bronstein => thinking,
+        };
+        self.remaining[color].saturating_sub(used)
+    }
+
+    /// Stops the clock of `color` after a move that took `thinking`, adding any increment.
+    /// Returns false, leaving no time on the clock, if its flag fell first.
+    pub fn press(&mut self, color: usize, thinking: Duration) -> bool {
+        let left = self.running(color, thinking);
+        if left.is_zero() {
+            self.remaining[color] = Duration::ZERO;
+            return false;
+        }
+
+        let bonus = match self.control.method {
+            Increment::Fischer => self.control.increment,
+            Increment::Bronstein => thinking.min(self.control.increment),
+            Increment::Delay => Duration::ZERO,
+        };
+        self.remaining[color] = left + bonus;
+        true
+    }
+
+    /// How long `color` can afford to think about its next move.
+    pub fn time_for_move(&self, color: usize) -> Duration {
+        time_for_move(self.remaining[color], self.control.increment, None)
+    }
+}
+
+/// Splits the time left over the moves still to come, assuming 30 when `moves_to_go` is
+/// not known, and spends most of the increment on top.
+pub fn time_for_move(
+    remaining: Duration,
+    increment: Duration,
+    moves_to_go: Option<u64>,
+) -> Duration {
+    let moves = moves_to_go.unwrap_or(MOVES_TO_GO).max(1) as u32;
+    let budget = remaining / moves + increment * 3 / 4;
+    budget
+        .min(remaining.saturating_sub(TIME_MARGIN))
+        .max(Duration::from_millis(1))
+}
+
+/// Formats time left the way clocks show it: `4:59`, `1:02:00`, or `9.8` with tenths under
+/// ten seconds.
+pub fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("{}.{}", seconds, time.subsec_millis() / 100)
+    } else if seconds < 3600 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
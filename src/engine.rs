@@ -0,0 +1,315 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chess_move::ChessMove;
+use crate::position::Position;
+use crate::search::Limit;
+use crate::variant::Variant;
+
+/// How long an engine gets to answer `uci`, `isready` and `stop`.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a search without a time limit may run before the engine is given up on.
+const UNTIMED_SEARCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Why talking to a UCI engine failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EngineError {
+    /// The engine could not be started or written to.
+    Io(String),
+    /// The engine did not answer a command in time.
+    NoResponse(String),
+    /// The engine closed its output.
+    Exited,
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Io(e) => write!(f, "{}", e),
+            EngineError::NoResponse(command) => {
+                write!(f, "the engine did not answer '{}'", command)
+            }
+            EngineError::Exited => write!(f, "the engine exited"),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+/// An engine evaluation, from the point of view of the side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    /// Centipawns.
+    Centipawns(i32),
+    /// Moves until mate, negative when the side to move is getting mated.
+    Mate(i32),
+}
+
+/// The fields of an `info` line that describe the search so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EngineInfo {
+    pub depth: Option<usize>,
+    pub score: Option<Score>,
+    /// The principal variation as UCI moves.
+    pub pv: Vec<String>,
+}
+
+/// Something an engine reported while searching.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EngineEvent {
+    Info(EngineInfo),
+    /// The move the engine chose, in UCI notation.
+    BestMove(String),
+}
+
+/// Parses a line of engine output, ignoring everything but search info and best moves.
+pub fn parse_engine_line(line: &str) -> Option<EngineEvent> {
+    let mut words = line.split_whitespace();
+    match words.next()? {
+        "bestmove" => Some(EngineEvent::BestMove(words.next()?.to_string())),
+        "info" => {
+            let mut info = EngineInfo::default();
+            while let Some(word) = words.next() {
+                match word {
+                    "depth" => info.depth = words.next().and_then(|n| n.parse().ok()),
+                    "score" => {
+                        let kind = words.next();
+                        let value = words.next().and_then(|n| n.parse().ok());
+                        info.score = match (kind, value) {
+                            (Some("cp"), Some(n)) => Some(Score::Centipawns(n)),
+                            (Some("mate"), Some(n)) => Some(Score::Mate(n)),
+                            _ => None,
+                        };
+                    }
+                    // The principal variation runs to the end of the line
+                    "pv" => info.pv = words.by_ref().map(str::to_string).collect(),
+                    // Free text also runs to the end of the line
+                    "string" => return None,
+                    _ => (),
+                }
+            }
+            if info.depth.is_none() && info.score.is_none() && info.pv.is_empty() {
+                None
+            } else {
+                Some(EngineEvent::Info(info))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A UCI engine running as a child process.
+pub struct Engine {
+    /// The name the engine gave in its `id name` line.
+    pub name: Option<String>,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    searching: bool,
+    /// When the current search should have sent its best move by.
+    deadline: Instant,
+    /// Whether the engine has been told to play Chess960.
+    chess960: bool,
+    /// The variant the engine has been told to play.
+    variant: Variant,
+}
+
+impl Engine {
+    /// Starts the engine at `path` and waits for it to finish its UCI handshake.
+    pub fn start(path: &str) -> Result<Self, EngineError> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| EngineError::Io(format!("{}: {}", path, e)))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // Reading happens on its own thread so the engine can be polled without blocking
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            name: None,
+            child,
+            stdin,
+            lines,
+            searching: false,
+            deadline: Instant::now(),
+            chess960: false,
+            variant: Variant::Standard,
+        };
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line("uci")?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.trim().to_string());
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.wait_until_ready()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| EngineError::Io(e.to_string()))
+    }
+
+    /// Waits for the next line of output, failing if the engine takes too long to answer
+    /// `command`.
+    fn read_line(&mut self, command: &str) -> Result<String, EngineError> {
+        match self.lines.recv_timeout(RESPONSE_TIMEOUT) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(EngineError::NoResponse(command.to_string())),
+            Err(RecvTimeoutError::Disconnected) => Err(EngineError::Exited),
+        }
+    }
+
+    fn wait_until_ready(&mut self) -> Result<(), EngineError> {
+        self.send("isready")?;
+        while self.read_line("isready")?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Tells the engine the next position belongs to a different game.
+    pub fn new_game(&mut self) -> Result<(), EngineError> {
+        self.stop()?;
+        self.send("ucinewgame")?;
+        self.wait_until_ready()
+    }
+
+    /// Sets the position reached by playing `moves` from `start`, switching the engine's
+    /// variant and Chess960 mode to match it. Engines that only play standard chess ignore
+    /// the variant.
+    pub fn set_position(
+        &mut self,
+        start: &Position,
+        moves: &[ChessMove],
+    ) -> Result<(), EngineError> {
+        if start.chess960 != self.chess960 {
+            self.send(&format!(
+                "setoption name UCI_Chess960 value {}",
+                start.chess960
+            ))?;
+            self.chess960 = start.chess960;
+        }
+        if start.variant != self.variant {
+            self.send(&format!(
+                "setoption name UCI_Variant value {}",
+                start.variant.key()
+            ))?;
+            self.variant = start.variant;
+        }
+        let mut command = format!("position fen {}", start.to_fen());
+        if !moves.is_empty() {
+            command += " moves";
+            for m in moves {
+                command.push(' ');
+                command += &m.to_uci();
+            }
+        }
+        self.send(&command)
+    }
+
+    /// Starts searching the current position.
+    pub fn go(&mut self, limit: Limit) -> Result<(), EngineError> {
+        let (command, timeout) = match limit {
+            Limit::Depth(depth) => (format!("go depth {}", depth), UNTIMED_SEARCH_TIMEOUT),
+            Limit::Time(time) => (
+                format!("go movetime {}", time.as_millis()),
+                time + RESPONSE_TIMEOUT,
+            ),
+            Limit::Nodes(nodes) => (format!("go nodes {}", nodes), UNTIMED_SEARCH_TIMEOUT),
+            Limit::Infinite => ("go infinite".to_string(), UNTIMED_SEARCH_TIMEOUT),
+        };
+        self.send(&command)?;
+        self.searching = true;
+        self.deadline = Instant::now() + timeout;
+        Ok(())
+    }
+
+    /// Ends the current search, returning the move it settled on.
+    pub fn stop(&mut self) -> Result<Option<String>, EngineError> {
+        if !self.searching {
+            return Ok(None);
+        }
+        self.send("stop")?;
+        loop {
+            if let Some(EngineEvent::BestMove(m)) = parse_engine_line(&self.read_line("stop")?) {
+                self.searching = false;
+                return Ok(Some(m));
+            }
+        }
+    }
+
+    /// The next thing the engine reported, without waiting for one.
+    pub fn poll(&mut self) -> Result<Option<EngineEvent>, EngineError> {
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => return Err(EngineError::Exited),
+            };
+            if let Some(event) = self.handle_line(&line) {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /// Waits for the current search to finish and returns its best move. An engine that
+    /// overruns the search limit is told to stop and reported as not answering.
+    pub fn best_move(&mut self) -> Result<String, EngineError> {
+        loop {
+            let timeout = self.deadline.saturating_duration_since(Instant::now());
+            let line = match self.lines.recv_timeout(timeout) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.send("stop")?;
+                    return Err(EngineError::NoResponse("go".to_string()));
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(EngineError::Exited),
+            };
+            if let Some(EngineEvent::BestMove(m)) = self.handle_line(&line) {
+                return Ok(m);
+            }
+        }
+    }
+
+    fn handle_line(&mut self, line: &str) -> Option<EngineEvent> {
+        let event = parse_engine_line(line)?;
+        if let EngineEvent::BestMove(_) = event {
+            self.searching = false;
+        }
+        Some(event)
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // Give the engine a moment to exit on its own before killing it
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
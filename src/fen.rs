@@ -0,0 +1,473 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chess_move::{parse_square, piece_from_letter, piece_letter};
+use crate::position::{square_name, Piece, Position, Square, POCKET_PIECES};
+use crate::variant::{Variant, CHECKS_TO_WIN};
+
+/// Why a FEN string could not be turned into a position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// FEN needs 6 fields, or 4 in the EPD form, plus one for Three-check counters.
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    /// A rank (1-8) that does not describe exactly 8 squares.
+    BadRankLength(usize),
+    BadPiece(char),
+    /// A side (0 White, 1 Black) with no king.
+    MissingKing(usize),
+    /// A side (0 White, 1 Black) with more than one king.
+    TooManyKings(usize),
+    PawnOnBackRank,
+    BadSideToMove(String),
+    /// A castling field that cannot be read, or castling rights in a variant without
+    /// castling.
+    BadCastling(String),
+    /// A castling right whose king or rook is not on its starting square.
+    CastlingMismatch(char),
+    BadEnPassant(String),
+    /// An en passant square that no pawn can have just skipped over.
+    ImpossibleEnPassant(String),
+    BadHalfmoveClock(String),
+    BadFullmoves(String),
+    /// A Three-check field that is not checks left, e.g. `3+2`, or checks given, e.g.
+    /// `+0+1`, or one given for another variant.
+    BadChecks(String),
+    /// A Crazyhouse pocket that is not a list of pieces other than kings, or that is not
+    /// closed, or one given for another variant.
+    BadPocket(String),
+    /// The side that just moved has left its king in check.
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let colour = |c: &usize| if *c == 0 { "White" } else { "Black" };
+        match self {
+            FenError::WrongFieldCount(n) => {
+                write!(f, "expected 6 fields (or 4 for EPD), found {}", n)
+            }
+            FenError::WrongRankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::BadRankLength(rank) => {
+                write!(f, "rank {} does not describe exactly 8 squares", rank)
+            }
+            FenError::BadPiece(c) => write!(f, "'{}' is not a piece", c),
+            FenError::MissingKing(c) => write!(f, "{} has no king", colour(c)),
+            FenError::TooManyKings(c) => write!(f, "{} has more than one king", colour(c)),
+            FenError::PawnOnBackRank => write!(f, "pawns cannot stand on the first or last rank"),
+            FenError::BadSideToMove(s) => write!(f, "side to move must be w or b, found '{}'", s),
+            FenError::BadCastling(s) => write!(f, "'{}' is not a valid castling field", s),
+            FenError::CastlingMismatch(c) => write!(
+                f,
+                "castling right '{}' needs its king and rook on their starting squares",
+                c
+            ),
+            FenError::BadEnPassant(s) => write!(f, "'{}' is not a valid en passant square", s),
+            FenError::ImpossibleEnPassant(s) => {
+                write!(f, "no pawn can have just moved past {}", s)
+            }
+            FenError::BadHalfmoveClock(s) => write!(f, "'{}' is not a valid halfmove clock", s),
+            FenError::BadFullmoves(s) => write!(f, "'{}' is not a valid move number", s),
+            FenError::BadChecks(s) => write!(f, "'{}' is not a valid Three-check field", s),
+            FenError::BadPocket(s) => write!(f, "'{}' is not a valid Crazyhouse pocket", s),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+impl FromStr for Position {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        Position::from_fen(fen)
+    }
+}
+
+impl Position {
+    /// The position as FEN. Chess960 castling rights are written as X-FEN, which is `KQkq`
+    /// unless the rook is not the outermost one on its side of the king.
+    pub fn to_fen(&self) -> String {
+        self.write_fen(false)
+    }
+
+    /// The position as Shredder-FEN, which gives every castling right as the file of its
+    /// rook, e.g. `HAha`. Reading it back always sets up a Chess960 game.
+    pub fn to_shredder_fen(&self) -> String {
+        self.write_fen(true)
+    }
+
+    fn write_fen(&self, shredder: bool) -> String {
+        let mut fen: String = "".to_string();
+        let mut empty_count: usize = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                let square = self.board[y][x];
+                if square.piece == Piece::Empty {
+                    empty_count += 1;
+                    continue;
+                }
+
+                if empty_count > 0 {
+                    fen += &empty_count.to_string();
+                    empty_count = 0;
+                }
+                if square.color == 0 {
+                    fen.push(piece_letter(square.piece));
+                } else {
+                    fen.push(piece_letter(square.piece).to_ascii_lowercase());
+                }
+                if self.promoted[y][x] {
+                    fen.push('~');
+                }
+            }
+            if empty_count > 0 {
+                fen += &empty_count.to_string();
+                empty_count = 0;
+            }
+
+            if y < 7 {
+                fen += "/";
+            }
+        }
+
+        if self.variant.has_drops() {
+            fen.push('[');
+            for color in 0..2 {
+                for &piece in POCKET_PIECES.iter().rev() {
+                    let letter = piece_letter(piece);
+                    let letter = if color == 0 {
+                        letter
+                    } else {
+                        letter.to_ascii_lowercase()
+                    };
+                    for _ in 0..self.pocket(color, piece) {
+                        fen.push(letter);
+                    }
+                }
+            }
+            fen.push(']');
+        }
+
+        if self.turn == 0 {
+            fen += " w ";
+        } else {
+            fen += " b ";
+        }
+
+        let mut castle_string = "".to_string();
+        for (color, side, c) in [(0, 0, 'K'), (0, 1, 'Q'), (1, 0, 'k'), (1, 1, 'q')] {
+            if !self.castling_rights[color][side] {
+                continue;
+            }
+            let rook_x = self.castling_rooks[color][side];
+            if shredder || self.outermost_rook(color, side) != Some(rook_x) {
+                let file = (b'a' + rook_x as u8) as char;
+                if color == 0 {
+                    castle_string.push(file.to_ascii_uppercase());
+                } else {
+                    castle_string.push(file);
+                }
+            } else {
+                castle_string.push(c);
+            }
+        }
+
+        if castle_string.is_empty() {
+            castle_string += "-";
+        }
+
+        fen += &castle_string;
+
+        match self.en_passant {
+            Some([x, y]) => fen += &format!(" {}", square_name(x, y)),
+            None => fen += " -",
+        }
+
+        if self.variant == Variant::ThreeCheck {
+            let [white, black] = self
+                .checks
+                .map(|checks| CHECKS_TO_WIN - checks.min(CHECKS_TO_WIN));
+            fen += &format!(" {}+{}", white, black);
+        }
+
+        fen += &format!(" {} {}", self.halfmove_clock, self.fullmoves);
+
+        fen
+    }
+
+    /// Parses and validates a FEN string. The 4 field EPD form is accepted, with the move
+    /// counters defaulting to `0 1`. A Three-check field makes it a Three-check position,
+    /// and a pocket a Crazyhouse one.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Self::from_variant_fen(fen, Variant::Standard)
+    }
+
+    /// Parses and validates a FEN string for a game of `variant`.
+    pub fn from_variant_fen(fen: &str, variant: Variant) -> Result<Self, FenError> {
+        let mut position = Self::empty();
+        position.variant = variant;
+        let mut contents: Vec<&str> = fen.split_whitespace().collect();
+        // Three-check counters come as checks left after the en passant square, or as checks
+        // given at the end
+        let checks = contents
+            .iter()
+            .position(|field| field.contains('+'))
+            .map(|i| contents.remove(i));
+        if contents.len() != 4 && contents.len() != 6 {
+            return Err(FenError::WrongFieldCount(
+                contents.len() + usize::from(checks.is_some()),
+            ));
+        }
+
+        let (pieces, pocket) = split_pocket(contents[0])?;
+        if let Some(pocket) = pocket {
+            position.fill_pocket(pocket)?;
+        }
+        position.fill_board(pieces)?;
+
+        position.turn = match contents[1] {
+            "w" => 0,
+            "b" => 1,
+            other => return Err(FenError::BadSideToMove(other.to_string())),
+        };
+
+        position.fill_castling_rights(contents[2])?;
+        position.fill_en_passant(contents[3])?;
+
+        if contents.len() == 6 {
+            position.halfmove_clock = contents[4]
+                .parse::<usize>()
+                .map_err(|_| FenError::BadHalfmoveClock(contents[4].to_string()))?;
+            position.fullmoves = match contents[5].parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(FenError::BadFullmoves(contents[5].to_string())),
+            };
+        }
+
+        if let Some(checks) = checks {
+            position.fill_checks(checks)?;
+        }
+
+        if position.king_attacked(1 - position.turn) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        Ok(position)
+    }
+
+    fn fill_board(&mut self, pieces: &str) -> Result<(), FenError> {
+        let lines: Vec<&str> = pieces.split('/').collect();
+        if lines.len() != 8 {
+            return Err(FenError::WrongRankCount(lines.len()));
+        }
+
+        let mut kings = [0, 0];
+        for (y, line) in lines.into_iter().enumerate() {
+            let mut x = 0;
+            for c in line.chars() {
+                if let Some(i) = c.to_digit(10) {
+                    if i == 0 {
+                        return Err(FenError::BadRankLength(8 - y));
+                    }
+                    x += i as usize;
+                    continue;
+                }
+
+                // Marks the piece before it as a promoted one, which only matters when
+                // captured pieces go to a pocket
+                if c == '~' && !self.variant.has_drops() {
+                    return Err(FenError::BadPiece(c));
+                }
+                if c == '~' && x > 0 && self.board[y][x - 1].piece != Piece::Empty {
+                    self.promoted[y][x - 1] = true;
+                    continue;
+                }
+
+                let piece = piece_from_letter(c).ok_or(FenError::BadPiece(c))?;
+                if x > 7 {
+                    return Err(FenError::BadRankLength(8 - y));
+                }
+
+                let color: usize = if c.is_ascii_uppercase() { 0 } else { 1 };
+                match piece {
+                    Piece::King => kings[color] += 1,
+                    Piece::Pawn if y == 0 || y == 7 => return Err(FenError::PawnOnBackRank),
+                    _ => (),
+                }
+                self.place_piece(piece, color, x, y);
+                x += 1;
+            }
+
+            if x != 8 {
+                return Err(FenError::BadRankLength(8 - y));
+            }
+        }
+
+        // Kings are ordinary pieces when they need not be kept safe
+        if !self.variant.has_royal_king() {
+            return Ok(());
+        }
+        for (color, count) in kings.into_iter().enumerate() {
+            match count {
+                0 => return Err(FenError::MissingKing(color)),
+                1 => (),
+                _ => return Err(FenError::TooManyKings(color)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads standard `KQkq` castling rights, X-FEN where `K` and `Q` mean the outermost rook
+    /// on that side, and Shredder-FEN rook files such as `HAha`. Anything but a king on the
+    /// e-file castling with a rook in the corner, or a field written with files, makes the
+    /// position a Chess960 one.
+    fn fill_castling_rights(&mut self, castling_rights: &str) -> Result<(), FenError> {
+        if castling_rights == "-" {
+            return Ok(());
+        }
+        if !self.variant.has_castling() {
+            return Err(FenError::BadCastling(castling_rights.to_string()));
+        }
+
+        for c in castling_rights.chars() {
+            let color = if c.is_ascii_uppercase() { 0 } else { 1 };
+            let y = if color == 0 { 7 } else { 0 };
+            let king_x = self.king_coords[color][0];
+            let (side, rook_x) = match c.to_ascii_lowercase() {
+                'k' => (0, self.outermost_rook(color, 0)),
+                'q' => (1, self.outermost_rook(color, 1)),
+                file @ 'a'..='h' => {
+                    let rook_x = (file as u8 - b'a') as usize;
+                    self.chess960 = true;
+                    (if rook_x > king_x { 0 } else { 1 }, Some(rook_x))
+                }
+                _ => return Err(FenError::BadCastling(castling_rights.to_string())),
+            };
+            if self.castling_rights[color][side] {
+                return Err(FenError::BadCastling(castling_rights.to_string()));
+            }
+
+            let Some(rook_x) = rook_x else {
+                return Err(FenError::CastlingMismatch(c));
+            };
+            if self.king_coords[color][1] != y
+                || self.board[y][rook_x] != Square::new(Piece::Rook, color)
+            {
+                return Err(FenError::CastlingMismatch(c));
+            }
+            if king_x != 4 || rook_x != [7, 0][side] {
+                self.chess960 = true;
+            }
+            self.castling_rights[color][side] = true;
+            self.castling_rooks[color][side] = rook_x;
+        }
+
+        Ok(())
+    }
+
+    /// The file of the rook of `color` furthest from its king on the given side, if its king
+    /// is on its back rank.
+    fn outermost_rook(&self, color: usize, side: usize) -> Option<usize> {
+        let y = if color == 0 { 7 } else { 0 };
+        let [king_x, king_y] = self.king_coords[color];
+        if king_y != y {
+            return None;
+        }
+        let rook = Square::new(Piece::Rook, color);
+        if side == 0 {
+            (king_x + 1..8).rev().find(|&x| self.board[y][x] == rook)
+        } else {
+            (0..king_x).find(|&x| self.board[y][x] == rook)
+        }
+    }
+
+    fn fill_pocket(&mut self, pocket: &str) -> Result<(), FenError> {
+        let error = || FenError::BadPocket(pocket.to_string());
+        if self.variant == Variant::Standard {
+            self.variant = Variant::Crazyhouse;
+        } else if !self.variant.has_drops() {
+            return Err(error());
+        }
+
+        for c in pocket.chars() {
+            let piece = piece_from_letter(c).ok_or_else(error)?;
+            let color = if c.is_ascii_uppercase() { 0 } else { 1 };
+            let i = POCKET_PIECES
+                .iter()
+                .position(|&p| p == piece)
+                .ok_or_else(error)?;
+            self.pockets[color][i] += 1;
+        }
+        Ok(())
+    }
+
+    fn fill_checks(&mut self, checks: &str) -> Result<(), FenError> {
+        let error = || FenError::BadChecks(checks.to_string());
+        if self.variant == Variant::Standard {
+            self.variant = Variant::ThreeCheck;
+        } else if self.variant != Variant::ThreeCheck {
+            return Err(error());
+        }
+
+        let (given, counts) = match checks.strip_prefix('+') {
+            Some(counts) => (true, counts),
+            None => (false, checks),
+        };
+        let (white, black) = counts.split_once('+').ok_or_else(error)?;
+        for (color, count) in [white, black].into_iter().enumerate() {
+            let count = match count.parse::<usize>() {
+                Ok(n) if n <= CHECKS_TO_WIN => n,
+                _ => return Err(error()),
+            };
+            self.checks[color] = if given { count } else { CHECKS_TO_WIN - count };
+        }
+        Ok(())
+    }
+
+    fn fill_en_passant(&mut self, en_passant: &str) -> Result<(), FenError> {
+        if en_passant == "-" {
+            return Ok(());
+        }
+
+        let [x, y] =
+            parse_square(en_passant).ok_or(FenError::BadEnPassant(en_passant.to_string()))?;
+
+        // The pawn that just moved two squares stands in front of the skipped square, and both
+        // the skipped square and the one it came from must be empty
+        let (expected_y, pawn_y, origin_y, mover) = if self.turn == 0 {
+            (2, 3, 1, 1)
+        } else {
+            (5, 4, 6, 0)
+        };
+        if y != expected_y
+            || self.board[pawn_y][x] != Square::new(Piece::Pawn, mover)
+            || self.board[y][x].piece != Piece::Empty
+            || self.board[origin_y][x].piece != Piece::Empty
+        {
+            return Err(FenError::ImpossibleEnPassant(en_passant.to_string()));
+        }
+
+        self.en_passant = Some([x, y]);
+        Ok(())
+    }
+}
+
+/// Splits a Crazyhouse pocket off the piece placement, written in brackets as in
+/// `RNBQKBNR[Qp]` or as a ninth rank as in `RNBQKBNR/Qp`.
+fn split_pocket(pieces: &str) -> Result<(&str, Option<&str>), FenError> {
+    if let Some((board, pocket)) = pieces.split_once('[') {
+        let pocket = pocket
+            .strip_suffix(']')
+            .ok_or_else(|| FenError::BadPocket(pocket.to_string()))?;
+        return Ok((board, Some(pocket)));
+    }
+    if pieces.matches('/').count() == 8 {
+        let (board, pocket) = pieces.rsplit_once('/').unwrap();
+        return Ok((board, Some(pocket)));
+    }
+    Ok((pieces, None))
+}
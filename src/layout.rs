@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+/// Square sizes tried when fitting the board to the terminal, smallest first. Terminal
+/// cells are about twice as tall as they are wide, so squares are wider than they are tall.
+pub const SQUARE_SIZES: [(u16, u16); 6] = [(1, 1), (3, 2), (5, 3), (7, 4), (9, 5), (11, 6)];
+
+/// Screen columns taken by the clocks, right of the board.
+pub const CLOCK_WIDTH: u16 = 18;
+/// Screen columns taken by each piece of a Crazyhouse pocket: the piece and its count.
+pub const POCKET_SLOT_WIDTH: u16 = 3;
+/// Screen columns taken by a move number in the move list, and by each move after it.
+pub const MOVE_NUMBER_WIDTH: u16 = 5;
+pub const MOVE_WIDTH: u16 = 9;
+/// Lines of text under the board: the hint, status, move entry, FEN, message and analysis
+/// lines.
+pub const LINES: u16 = 6;
+
+/// Where things are drawn on the screen for squares `square_width` columns wide and
+/// `square_height` rows tall. The board is drawn from the top left of the screen with the
+/// rank labels left of it and the file labels under it, the lines of text under those, and
+/// the clocks and move list to its right. Board columns and rows are counted from the top
+/// left as the board is seen, whichever way round it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub square_width: u16,
+    pub square_height: u16,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new(SQUARE_SIZES[0])
+    }
+}
+
+/// Reads a square size written as `<width>x<height>`, e.g. `5x3`.
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("'{}' is not a square size, expected e.g. 5x3", s);
+        let (width, height) = s.split_once(['x', 'X']).ok_or_else(error)?;
+        let size = |n: &str| match n.parse::<u16>() {
+            Ok(n @ 1..=20) => Ok(n),
+            _ => Err(error()),
+        };
+        Ok(Self::new((size(width)?, size(height)?)))
+    }
+}
+
+impl Layout {
+    pub fn new((square_width, square_height): (u16, u16)) -> Self {
+        Self {
+            square_width,
+            square_height,
+        }
+    }
+
+    /// The layout with the largest squares that fit a terminal of `columns` by `rows`, or
+    /// the smallest squares when none do.
+    pub fn fit(columns: u16, rows: u16) -> Self {
+        SQUARE_SIZES
+            .iter()
+            .rev()
+            .map(|&size| Self::new(size))
+            .find(|layout| layout.width() <= columns && layout.height() <= rows)
+            .unwrap_or_default()
+    }
+
+    /// Screen columns needed to show everything.
+    pub fn width(&self) -> u16 {
+        self.move_list_column() + MOVE_NUMBER_WIDTH + 2 * MOVE_WIDTH - 1
+    }
+
+    /// Screen rows needed to show everything.
+    pub fn height(&self) -> u16 {
+        self.label_row() + LINES
+    }
+
+    /// The screen column and row of the top left cell of the square in board column `col`
+    /// and row `row`.
+    pub fn square_origin(&self, col: u16, row: u16) -> (u16, u16) {
+        (2 + col * self.square_width, 1 + row * self.square_height)
+    }
+
+    /// The screen column and row of the middle cell of a square, where its piece is drawn.
+    pub fn square_centre(&self, col: u16, row: u16) -> (u16, u16) {
+        let (x, y) = self.square_origin(col, row);
+        (x + self.square_width / 2, y + self.square_height / 2)
+    }
+
+    /// The board column and row of the square covering screen column `x` and row `y`.
+    pub fn square_at(&self, x: u16, y: u16) -> Option<(u16, u16)> {
+        let col = x.checked_sub(2)? / self.square_width;
+        let row = y.checked_sub(1)? / self.square_height;
+        (col < 8 && row < 8).then_some((col, row))
+    }
+
+    /// The screen row of the file labels.
+    pub fn label_row(&self) -> u16 {
+        8 * self.square_height + 1
+    }
+
+    /// The screen row of line `line` of the text under the board, counting from 0.
+    pub fn line_row(&self, line: u16) -> u16 {
+        self.label_row() + 1 + line
+    }
+
+    /// The screen column the clocks start at, leaving a gap after the board.
+    pub fn clock_column(&self) -> u16 {
+        2 + 8 * self.square_width + 2
+    }
+
+    /// The screen rows of the clocks by the top and bottom edges of the board.
+    pub fn clock_rows(&self) -> (u16, u16) {
+        (1, 8 * self.square_height)
+    }
+
+    /// The screen rows of the Crazyhouse pockets, just inside the clocks.
+    pub fn pocket_rows(&self) -> (u16, u16) {
+        let (top, bottom) = self.clock_rows();
+        (top + 1, bottom - 1)
+    }
+
+    /// The screen column of slot `slot` of a pocket.
+    pub fn pocket_slot_column(&self, slot: usize) -> u16 {
+        self.clock_column() + 1 + slot as u16 * POCKET_SLOT_WIDTH
+    }
+
+    /// The pocket row, top being true, and the slot covering screen column `x` and row `y`.
+    pub fn pocket_slot_at(&self, x: u16, y: u16, slots: usize) -> Option<(bool, usize)> {
+        let (top, bottom) = self.pocket_rows();
+        let slot = (x.checked_sub(self.clock_column() + 1)? / POCKET_SLOT_WIDTH) as usize;
+        if slot >= slots {
+            None
+        } else if y == top {
+            Some((true, slot))
+        } else if y == bottom {
+            Some((false, slot))
+        } else {
+            None
+        }
+    }
+
+    pub fn move_list_column(&self) -> u16 {
+        self.clock_column() + CLOCK_WIDTH
+    }
+
+    /// Rows of moves the move list shows at once, as tall as the board and its labels.
+    pub fn move_list_rows(&self) -> usize {
+        self.label_row() as usize
+    }
+}
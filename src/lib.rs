@@ -0,0 +1,27 @@
+//! Chess rules for chess-term, independent of any terminal frontend.
+
+mod chess_move;
+pub mod clock;
+pub mod engine;
+mod fen;
+pub mod layout;
+pub mod network;
+pub mod perft;
+pub mod pgn;
+mod position;
+mod record;
+mod san;
+pub mod search;
+pub mod theme;
+pub mod uci;
+mod variant;
+
+pub use chess_move::{parse_square, piece_from_letter, piece_letter, ChessMove};
+pub use fen::FenError;
+pub use position::{
+    get_change_from_move, square_name, GameStatus, Move, Piece, Position, RepetitionKey, Square,
+    CHESS960_POSITIONS, POCKET_PIECES,
+};
+pub use record::{DrawClaim, GameRecord, GameResult};
+pub use san::SanError;
+pub use variant::{Variant, CHECKS_TO_WIN};
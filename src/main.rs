@@ -1,1123 +1,1385 @@
+extern crate clap;
 extern crate clipboard;
 extern crate termion;
-extern crate clap;
 
 use termion::event::*;
 use termion::input::{MouseTerminal, TermRead};
 use termion::raw::IntoRawMode;
 use termion::{color, style};
 
-use rayon::prelude::*;
-
-use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
-
 use clipboard::{ClipboardContext, ClipboardProvider};
 
-use clap::Parser;
-
-use std::io::{stdin, stdout, Read, Write};
-
-#[derive(Parser)]#[command(name = "MyApp")]
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+
+use chess_term::clock::{format_clock, Clock, Increment, TimeControl};
+use chess_term::engine::{Engine, EngineError, EngineEvent, EngineInfo, Score};
+use chess_term::layout::{Layout, MOVE_NUMBER_WIDTH, MOVE_WIDTH};
+use chess_term::network::{Connection, Host, Message, NetError, NetEvent};
+use chess_term::perft::{divide, perft};
+use chess_term::pgn::{parse_games, today};
+use chess_term::search::{search, Limit};
+use chess_term::theme::{Config, ConfigError, Palette, PieceSet, Theme, BUILTIN_THEMES};
+use chess_term::{
+    piece_from_letter, piece_letter, ChessMove, DrawClaim, GameRecord, GameResult, GameStatus,
+    Piece, Position, SanError, Square, Variant, CHECKS_TO_WIN, CHESS960_POSITIONS, POCKET_PIECES,
+};
+
+use std::fs;
+use std::io::{stdin, stdout, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{iter, thread};
+
+#[derive(Parser)]
+#[command(name = "MyApp")]
 #[command(about = "Play chess in your terminal", long_about = None)]
 #[command(author, version)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Set position from given FEN string
     #[arg(short, long)]
     fen: Option<String>,
+
+    /// Play a chess variant: chess, kingofthehill, 3check, racingkings, atomic, antichess or
+    /// crazyhouse
+    #[arg(long, value_parser = Variant::from_str)]
+    variant: Option<Variant>,
+
+    /// Play Chess960 from start position <N>, 0 to 959 with 518 the standard one, or from a
+    /// random one if left out
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        value_parser = clap::value_parser!(u16).range(0..CHESS960_POSITIONS as i64),
+        conflicts_with = "fen"
+    )]
+    chess960: Option<Option<u16>>,
+
+    /// Open a game from a PGN file and step through it with the arrow keys
+    #[arg(long, conflicts_with_all = ["fen", "chess960", "variant"])]
+    pgn: Option<String>,
+
+    /// Which game of the PGN file to open, starting from 1
+    #[arg(long, default_value_t = 1, requires = "pgn")]
+    game: usize,
+
+    /// File the game is saved to as PGN with the s key
+    #[arg(long, default_value = "game.pgn")]
+    save_pgn: String,
+
+    /// Play against the computer, taking the given side (white if left out)
+    #[arg(
+        long,
+        value_name = "COLOUR",
+        num_args = 0..=1,
+        default_missing_value = "white"
+    )]
+    vs_computer: Option<Side>,
+
+    /// How many plies deep the computer searches, instead of thinking for a set time
+    #[arg(long, requires = "vs_computer", conflicts_with = "move_time")]
+    depth: Option<usize>,
+
+    /// Milliseconds the computer thinks about each move [default: 1000, or a share of its
+    /// clock with --time]
+    #[arg(long, value_name = "MS", requires = "vs_computer")]
+    move_time: Option<u64>,
+
+    /// UCI engine to play against with --vs-computer, or to analyse the game with otherwise
+    #[arg(long, value_name = "PATH")]
+    engine: Option<String>,
+
+    /// Play on the clock: minutes per side plus seconds added per move, e.g. 5+3
+    #[arg(long, value_name = "CONTROL", value_parser = TimeControl::from_str)]
+    time: Option<TimeControl>,
+
+    /// Use the seconds per move of --time as a delay rather than a Fischer increment
+    #[arg(long, value_name = "KIND", requires = "time")]
+    delay: Option<Delay>,
+
+    /// Show the board from Black's side, as it is when playing Black against the computer
+    #[arg(long)]
+    flip: bool,
+
+    /// Turn the board round after every move so the side to move plays up the screen
+    #[arg(long, conflicts_with = "vs_computer")]
+    auto_flip: bool,
+
+    /// Host a game over the network on <PORT>, playing White against whoever connects
+    #[arg(
+        long,
+        value_name = "PORT",
+        conflicts_with_all = ["pgn", "vs_computer", "auto_flip", "time"]
+    )]
+    host: Option<u16>,
+
+    /// Colour theme for the board: one of the config file's themes or a built-in one
+    /// (classic, wood, ocean, mono)
+    #[arg(long, value_name = "NAME")]
+    theme: Option<String>,
+
+    /// Colours the terminal can show [default: worked out from NO_COLOR, COLORTERM and TERM]
+    #[arg(long, value_name = "PALETTE")]
+    colors: Option<Colors>,
+
+    /// Draw pieces as chess symbols or letters [default: symbols in a UTF-8 locale]
+    #[arg(long, value_name = "SET")]
+    pieces: Option<Pieces>,
+
+    /// Size of the squares in screen columns and rows, e.g. 5x3 [default: the largest that
+    /// fits the terminal]
+    #[arg(long, value_name = "WxH", value_parser = Layout::from_str)]
+    square_size: Option<Layout>,
+
+    /// Config file to read instead of ~/.config/chess-term/config.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Join the game hosted at <ADDR>, e.g. 192.168.1.20:4000, playing Black
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = [
+            "host", "fen", "chess960", "variant", "pgn", "vs_computer", "auto_flip", "time"
+        ]
+    )]
+    connect: Option<String>,
 }
 
-enum KeyCaptureState {
-    Gameplay,
-    EditBoard,
-    ChooseColour,
-    PromotePawn,
-    ExitGame,
+#[derive(Clone, Copy, ValueEnum)]
+enum Side {
+    White,
+    Black,
 }
 
-#[derive(Clone, PartialEq)]
-enum Piece {
-    King,
-    Queen,
-    Rook,
-    Bishop,
-    Knight,
-    Pawn,
-    Empty,
+#[derive(Clone, Copy, ValueEnum)]
+enum Colors {
+    /// 24-bit colour
+    Truecolor,
+    #[value(name = "256")]
+    Ansi256,
+    #[value(name = "16")]
+    Ansi16,
+    /// No colour: squares and highlights are told apart by symbols and text styles
+    None,
 }
 
-#[derive(Clone, PartialEq, EnumIter)]
-enum Move {
-    U,
-    D,
-    R,
-    L,
-    UL,
-    DL,
-    UR,
-    DR,
-    RRU,
-    RUU,
-    RRD,
-    RDD,
-    LUU,
-    LLU,
-    LLD,
-    LDD,
+#[derive(Clone, Copy, ValueEnum)]
+enum Pieces {
+    /// ♔♕♖♗♘♙ and ♚♛♜♝♞♟
+    Unicode,
+    /// KQRBNP for White and kqrbnp for Black
+    Ascii,
 }
 
-#[derive(Clone)]
-struct Square {
-    icon: char,
+#[derive(Clone, Copy, ValueEnum)]
+enum Delay {
+    /// The clock waits for the delay before it starts running
+    Simple,
+    /// Time used is given back after the move, up to the delay
+    Bronstein,
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum Commands {
+    /// Count the positions reachable in <DEPTH> moves to check the move generator
+    Perft {
+        depth: usize,
+
+        /// Count from the given FEN string instead of the starting position
+        #[arg(short, long, value_parser = Position::from_fen)]
+        fen: Option<Position>,
+
+        /// Print the count below every legal move
+        #[arg(short, long)]
+        divide: bool,
+    },
+    /// Run as a UCI engine on stdin and stdout, for chess GUIs and engine matches
+    Uci,
+}
+
+/// The side the program, or the engine if there is one, plays and how long it may think.
+/// Without a limit it thinks for a share of the time left on its clock.
+#[derive(Clone, Copy)]
+struct Computer {
     color: usize,
-    piece: Piece,
-    moves: Vec<Move>,
-    is_valid_move: bool,
+    limit: Option<Limit>,
+}
+
+/// How the game was set up on the command line.
+struct Settings {
+    pgn_path: String,
+    replay: bool,
+    computer: Option<Computer>,
+    engine: Option<Engine>,
+    time_control: Option<TimeControl>,
+    flipped: bool,
+    auto_flip: bool,
+    network: Option<Connection>,
+    theme: Theme,
+    palette: Palette,
+    pieces: PieceSet,
+    /// The square size asked for, instead of fitting the board to the terminal.
+    layout: Option<Layout>,
+}
+
+enum KeyCaptureState {
+    Gameplay,
+    EditBoard,
+    ChooseColour,
+    PromotePawn,
+    ChooseDrop,
+    TypeMove,
+    Replay,
+    ExitGame,
 }
 
 struct Game<R, W> {
-    board: Vec<Vec<Square>>,
+    position: Position,
     x: usize,
     y: usize,
     cursor_x: u16,
     cursor_y: u16,
-    turn: usize,
-    king_in_check: bool,
     selected_piece: [usize; 2],
-    en_passant: Vec<[usize; 2]>,
-    castling_rights: [[bool; 2]; 2],
-    king_coords: [[usize; 2]; 2],
-    moves: Vec<[usize; 2]>,
+    /// The moves shown on the board: those of the selected piece, or the drops of
+    /// `drop_piece`.
+    moves: Vec<ChessMove>,
+    /// The pocket piece picked to drop, in Crazyhouse.
+    drop_piece: Option<Piece>,
+    /// The move that led to the position shown, tinted on the board.
+    last_move: Option<ChessMove>,
+    /// The square of the king in check in the position shown.
+    checked_king: Option<[usize; 2]>,
+    record: GameRecord,
+    ply: usize,
+    pgn_path: String,
     show_fen: bool,
-    halfmove_clock: usize,
-    fullmoves: usize,
-    initial_fen: Option<String>,
+    theme: Theme,
+    palette: Palette,
+    pieces: PieceSet,
+    layout: Layout,
+    /// Whether the square size was given, rather than fitted to the terminal.
+    fixed_layout: bool,
+    /// The terminal size the screen was last drawn for.
+    terminal_size: Option<(u16, u16)>,
+    /// The text on each line under the board, kept to draw it again after a resize.
+    lines: [String; Line::ALL.len()],
+    /// The move being typed, while one is.
+    input: Option<String>,
+    /// Whether Black is at the bottom of the screen.
+    flipped: bool,
+    auto_flip: bool,
+    computer: Option<Computer>,
+    engine: Option<Engine>,
+    /// The position the engine is analysing, if it is.
+    analysis: Option<Position>,
+    network: Option<Connection>,
+    /// The colour played on this terminal in a network game, kept after the connection is
+    /// lost so neither side can be moved any more.
+    network_color: Option<usize>,
+    /// The side with a draw offer waiting for an answer.
+    draw_offer: Option<usize>,
+    /// The first row of moves the move list shows, counting from the first move.
+    move_list_top: usize,
+    /// The move highlighted in the move list, as the number of plies played after it.
+    move_list_ply: usize,
+    clock: Option<Clock>,
+    /// When the side to move started thinking, or None while the clocks are stopped to
+    /// edit the board or step through the game.
+    move_started: Option<Instant>,
+    game_over: bool,
     stdout: W,
     stdin: R,
 }
 
-impl Square {
-    pub fn new(piece: Piece, color: usize) -> Self {
-        Self {
-            icon: match piece {
-                Piece::King => {
-                    if color == 0 {
-                        '♔'
-                    } else {
-                        '♚'
-                    }
-                }
-                Piece::Queen => {
-                    if color == 0 {
-                        '♕'
-                    } else {
-                        '♛'
-                    }
-                }
-                Piece::Rook => {
-                    if color == 0 {
-                        '♖'
-                    } else {
-                        '♜'
-                    }
-                }
-                Piece::Bishop => {
-                    if color == 0 {
-                        '♗'
-                    } else {
-                        '♝'
-                    }
-                }
-                Piece::Knight => {
-                    if color == 0 {
-                        '♘'
-                    } else {
-                        '♞'
-                    }
-                }
-                Piece::Pawn => {
-                    if color == 0 {
-                        '\u{2659}'
-                    } else {
-                        '\u{265F}'
-                    }
-                }
-                _ => ' ',
-            },
-            moves: match piece {
-                Piece::King => vec![
-                    Move::U,
-                    Move::D,
-                    Move::L,
-                    Move::R,
-                    Move::UR,
-                    Move::UL,
-                    Move::DR,
-                    Move::DL,
-                ],
-                Piece::Queen => vec![
-                    Move::U,
-                    Move::D,
-                    Move::L,
-                    Move::R,
-                    Move::UR,
-                    Move::UL,
-                    Move::DR,
-                    Move::DL,
-                ],
-                Piece::Rook => vec![Move::U, Move::D, Move::L, Move::R],
-                Piece::Bishop => vec![Move::UR, Move::UL, Move::DR, Move::DL],
-                Piece::Knight => vec![
-                    Move::RRU,
-                    Move::RUU,
-                    Move::RRD,
-                    Move::RDD,
-                    Move::LLU,
-                    Move::LUU,
-                    Move::LLD,
-                    Move::LDD,
-                ],
-                Piece::Pawn => {
-                    if color == 0 {
-                        vec![Move::U, Move::UL, Move::UR]
-                    } else {
-                        vec![Move::D, Move::DL, Move::DR]
-                    }
-                }
-                _ => vec![],
-            },
-            piece,
-            color,
-            is_valid_move: false,
-        }
+/// The lines of text under the board, from the top.
+#[derive(Clone, Copy)]
+enum Line {
+    Hint,
+    Status,
+    /// The move being typed.
+    Input,
+    Fen,
+    Message,
+    Analysis,
+}
+
+/// Shown before the move being typed.
+const INPUT_PROMPT: &str = "Move: ";
+
+impl Line {
+    const ALL: [Line; 6] = [
+        Line::Hint,
+        Line::Status,
+        Line::Input,
+        Line::Fen,
+        Line::Message,
+        Line::Analysis,
+    ];
+}
+
+fn get_icon(square: &Square, pieces: PieceSet) -> char {
+    if pieces == PieceSet::Ascii {
+        let letter = piece_letter(square.piece);
+        return if square.color == 1 {
+            letter.to_ascii_lowercase()
+        } else {
+            letter
+        };
     }
+    match (square.piece, square.color) {
+        (Piece::King, 0) => '♔',
+        (Piece::King, _) => '♚',
+        (Piece::Queen, 0) => '♕',
+        (Piece::Queen, _) => '♛',
+        (Piece::Rook, 0) => '♖',
+        (Piece::Rook, _) => '♜',
+        (Piece::Bishop, 0) => '♗',
+        (Piece::Bishop, _) => '♝',
+        (Piece::Knight, 0) => '♘',
+        (Piece::Knight, _) => '♞',
+        (Piece::Pawn, 0) => '\u{2659}',
+        (Piece::Pawn, _) => '\u{265F}',
+        (Piece::Empty, _) => ' ',
+    }
+}
+
+/// The longest start shared by all of `words`.
+fn common_prefix(words: &[String]) -> &str {
+    let Some(first) = words.first() else {
+        return "";
+    };
+    let mut len = first.len();
+    for word in &words[1..] {
+        len = first
+            .bytes()
+            .zip(word.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+    &first[..len]
+}
+
+fn new_record(
+    position: &Position,
+    computer: Option<Computer>,
+    engine: Option<&Engine>,
+    time_control: Option<TimeControl>,
+) -> GameRecord {
+    let mut record = GameRecord::new(position.clone());
+    record.set_tag("Event", "Casual game");
+    record.set_tag("Site", "chess-term");
+    record.set_tag("Date", &today());
+    if let Some(time_control) = time_control {
+        record.set_tag("TimeControl", &time_control.pgn_tag());
+    }
+    if let Some(computer) = computer {
+        let side = if computer.color == 0 {
+            "White"
+        } else {
+            "Black"
+        };
+        let name = engine.and_then(|engine| engine.name.as_deref());
+        record.set_tag(side, name.unwrap_or("chess-term"));
+    }
+    record
 }
 
-fn init_game<R: Read, W: Write>(stdout: W, stdin: R, fen: Option<String>) {
+fn init_game<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write>(
+    stdout: W,
+    stdin: R,
+    record: GameRecord,
+    settings: Settings,
+) {
     let mut game = Game {
-        board: Vec::new(),
+        position: record.position(),
+        record,
+        ply: 0,
+        pgn_path: settings.pgn_path,
         x: 0,
         y: 0,
         cursor_x: 2,
         cursor_y: 1,
-        turn: 0,
-        king_in_check: false,
         selected_piece: [0, 0],
-        castling_rights: [[true, true], [true, true]],
-        king_coords: [[4, 7], [4, 0]],
-        en_passant: vec![],
         moves: Vec::new(),
+        drop_piece: None,
+        last_move: None,
+        checked_king: None,
         show_fen: false,
-        halfmove_clock: 0,
-        fullmoves: 1,
-        initial_fen: fen,
+        theme: settings.theme,
+        palette: settings.palette,
+        pieces: settings.pieces,
+        layout: settings.layout.unwrap_or_default(),
+        fixed_layout: settings.layout.is_some(),
+        terminal_size: None,
+        lines: Default::default(),
+        input: None,
+        flipped: settings.flipped,
+        auto_flip: settings.auto_flip,
+        computer: settings.computer,
+        engine: settings.engine,
+        analysis: None,
+        network_color: settings.network.as_ref().map(|network| network.color),
+        network: settings.network,
+        draw_offer: None,
+        move_list_top: 0,
+        move_list_ply: 0,
+        clock: settings.time_control.map(Clock::new),
+        move_started: None,
+        game_over: false,
         stdout,
-        stdin: stdin.events(),
+        stdin,
     };
 
-    game.start();
+    game.start(settings.replay);
 }
 
-fn get_change_from_move(m: &Move) -> [isize; 2] {
-    match m {
-        Move::U => [0, -1],
-        Move::D => [0, 1],
-        Move::R => [1, 0],
-        Move::L => [-1, 0],
-        Move::UR => [1, -1],
-        Move::UL => [-1, -1],
-        Move::DR => [1, 1],
-        Move::DL => [-1, 1],
-        Move::RRU => [2, -1],
-        Move::RUU => [1, -2],
-        Move::RRD => [2, 1],
-        Move::RDD => [1, 2],
-        Move::LLU => [-2, -1],
-        Move::LUU => [-1, -2],
-        Move::LLD => [-2, 1],
-        Move::LDD => [-1, 2],
+fn load_pgn(path: &str, game: usize) -> Result<GameRecord, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut games = parse_games(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if game == 0 || game > games.len() {
+        return Err(format!(
+            "{}: there is no game {}, the file has {} game(s)",
+            path,
+            game,
+            games.len()
+        ));
     }
+    Ok(games.swap_remove(game - 1))
 }
 
 impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
-    fn init_board(&mut self) {
-        // First row
-        let mut row: Vec<Square> = vec![
-            Square::new(Piece::Rook, 1),
-            Square::new(Piece::Knight, 1),
-            Square::new(Piece::Bishop, 1),
-            Square::new(Piece::Queen, 1),
-            Square::new(Piece::King, 1),
-            Square::new(Piece::Bishop, 1),
-            Square::new(Piece::Knight, 1),
-            Square::new(Piece::Rook, 1),
-        ];
-        self.board.push(row);
-
-        row = Vec::new();
-        for _ in 0..8 {
-            row.push(Square::new(Piece::Pawn, 1));
-        }
-        self.board.push(row);
-
-        for _ in 0..4 {
-            row = Vec::new();
-            for _ in 0..8 {
-                row.push(Square::new(Piece::Empty, 2));
+    fn get_bg_color(&self, x: u16, y: u16) -> String {
+        let [x, y] = [x as usize, y as usize];
+        let no_color = self.palette == Palette::NoColor;
+        if self.checked_king == Some([x, y]) {
+            if no_color {
+                return format!("{}{}", style::Bold, style::Invert);
             }
-            self.board.push(row);
+            return self.palette.bg(self.theme.check);
         }
-
-        row = Vec::new();
-        for _ in 0..8 {
-            row.push(Square::new(Piece::Pawn, 0));
+        if let Some(m) = self.last_move {
+            if m.from == [x, y] || m.to == [x, y] {
+                if no_color {
+                    return style::Underline.to_string();
+                }
+                return self.palette.bg(self.theme.last_move);
+            }
         }
-        self.board.push(row);
 
-        row = vec![
-            Square::new(Piece::Rook, 0),
-            Square::new(Piece::Knight, 0),
-            Square::new(Piece::Bishop, 0),
-            Square::new(Piece::Queen, 0),
-            Square::new(Piece::King, 0),
-            Square::new(Piece::Bishop, 0),
-            Square::new(Piece::Knight, 0),
-            Square::new(Piece::Rook, 0),
-        ];
-        self.board.push(row);
+        if (x + y).is_multiple_of(2) {
+            self.palette.bg(self.theme.light_square)
+        } else {
+            self.palette.bg(self.theme.dark_square)
+        }
     }
 
-    fn get_bg_color(&self, x: u16, y: u16) -> String {
-        let white = color::Bg(color::Rgb(200, 200, 200)).to_string();
-        let black = color::Bg(color::LightGreen).to_string();
+    fn highlight_color(&self) -> String {
+        if self.palette == Palette::NoColor {
+            style::Invert.to_string()
+        } else {
+            self.palette.bg(self.theme.highlight)
+        }
+    }
 
-        if x % 2 == 0 {
-            if y % 2 == 0 {
-                white
-            } else {
-                black
-            }
-        } else if y % 2 == 0 {
-            black
+    /// The style of the hint line and game over messages.
+    fn alert_color(&self) -> String {
+        if self.palette == Palette::NoColor {
+            style::Invert.to_string()
         } else {
-            white
+            color::Bg(color::Red).to_string()
+        }
+    }
+
+    /// What is drawn on the square at `x`, `y`. Without colour, empty dark squares are
+    /// dotted so the squares can still be told apart.
+    fn icon(&self, x: usize, y: usize) -> char {
+        let square = self.position.square(x, y);
+        if square.piece == Piece::Empty
+            && self.palette == Palette::NoColor
+            && !(x + y).is_multiple_of(2)
+        {
+            return '.';
         }
+        get_icon(&square, self.pieces)
+    }
+
+    /// Marks the move that led to the position `ply` plies into the game, and the king it
+    /// left in check, to be tinted the next time their squares are drawn.
+    fn mark_last_move(&mut self, ply: usize) {
+        self.last_move = ply.checked_sub(1).map(|i| self.record.moves()[i]);
+        self.checked_king = self
+            .position
+            .in_check()
+            .then(|| self.position.king_coords[self.position.turn]);
     }
 
     fn print_initial_board(&mut self) {
-        write!(
-            self.stdout,
-            "{}{}",
-            termion::clear::All,
-            termion::cursor::Goto(1, 1)
-        )
-        .unwrap();
+        write!(self.stdout, "{}", termion::clear::All).unwrap();
+        self.print_board();
+    }
 
-        for y in 0..8 {
+    /// Draws the squares with the rank and file labels around them, the way round the
+    /// board is being viewed.
+    fn print_board(&mut self) {
+        let (width, height) = (self.layout.square_width, self.layout.square_height);
+        for line in 0..8 * height {
+            let row = line / height;
+            let rank = if self.flipped { row + 1 } else { 8 - row };
+            // Each rank is labelled level with its pieces
+            let label = if line % height == height / 2 {
+                rank.to_string()
+            } else {
+                " ".to_string()
+            };
             write!(
                 self.stdout,
-                "{}{}{}",
-                termion::cursor::Goto(1, y + 1),
-                color::Bg(color::Blue),
-                8 - y
+                "{}{}{}{}{}",
+                termion::cursor::Goto(1, line + 1),
+                self.palette.bg(self.theme.label),
+                self.palette.fg(self.theme.label_text),
+                label,
+                style::Reset
             )
             .unwrap();
-
-            for x in 0..8 {
-                write!(
-                    self.stdout,
-                    "{}{}",
-                    self.get_bg_color(x, y),
-                    self.board[y as usize][x as usize].icon
-                )
-                .unwrap();
-            }
         }
 
         write!(
             self.stdout,
-            "{}{} ",
-            color::Bg(color::Blue),
-            termion::cursor::Goto(1, 9)
+            "{}{}{} ",
+            self.palette.bg(self.theme.label),
+            self.palette.fg(self.theme.label_text),
+            termion::cursor::Goto(1, self.layout.label_row())
         )
         .unwrap();
 
-        for i in 0..8 {
-            write!(self.stdout, "{}", char::from_u32(i as u32 + 65).unwrap()).unwrap();
+        for col in 0..8 {
+            let file = if self.flipped { 7 - col } else { col };
+            write!(
+                self.stdout,
+                "{}{}{}",
+                " ".repeat((width / 2) as usize),
+                char::from_u32(file + 65).unwrap(),
+                " ".repeat((width - width / 2 - 1) as usize)
+            )
+            .unwrap();
         }
 
         write!(self.stdout, "{}", style::Reset).unwrap();
+        self.redraw_board();
         self.stdout.flush().unwrap();
     }
 
-    //FEN helper functions
-    fn create_fen_string(&mut self) -> String {
-        let mut fen: String = "".to_string();
-        let mut empty_count: usize = 0;
-        let mut piece_char: char;
-        for y in 0..8 {
-            for x in 0..8 {
-                match self.board[y][x].piece {
-                    Piece::King => piece_char = 'k',
-                    Piece::Queen => piece_char = 'q',
-                    Piece::Rook => piece_char = 'r',
-                    Piece::Bishop => piece_char = 'b',
-                    Piece::Knight => piece_char = 'n',
-                    Piece::Pawn => piece_char = 'p',
-                    Piece::Empty => {
-                        piece_char = ' ';
-                        empty_count += 1;
-                    }
-                }
-
-                if piece_char != ' ' {
-                    if empty_count > 0 {
-                        fen += &empty_count.to_string();
-                        empty_count = 0;
-                    }
-                    if self.board[y][x].color == 0 {
-                        piece_char = piece_char.to_uppercase().next().unwrap();
-                    }
-                    fen += &piece_char.to_string();
-                }
-            }
-            if empty_count > 0 {
-                fen += &empty_count.to_string();
-                empty_count = 0;
-            }
-
-            if y < 7 {
-                fen += "/";
-            }
-        }
+    /// The board column and row the square at `x`, `y` is seen in.
+    fn board_coords(&self, x: usize, y: usize) -> (u16, u16) {
+        let (col, row) = if self.flipped { (7 - x, 7 - y) } else { (x, y) };
+        (col as u16, row as u16)
+    }
 
-        if self.turn == 0 {
-            fen += " w ";
-        } else {
-            fen += " b ";
-        }
+    /// The screen column and row of the middle of the square at `x`, `y` on the board.
+    fn screen_coords(&self, x: usize, y: usize) -> (u16, u16) {
+        let (col, row) = self.board_coords(x, y);
+        self.layout.square_centre(col, row)
+    }
 
-        let mut castle_string = "".to_string();
-        if self.castling_rights[0][0] {
-            castle_string += "K";
+    /// Fits the board to the terminal, drawing everything again when its size changed.
+    fn fit_to_terminal(&mut self) {
+        let Ok(size) = termion::terminal_size() else {
+            return;
+        };
+        if self.terminal_size == Some(size) {
+            return;
         }
-
-        if self.castling_rights[0][1] {
-            castle_string += "Q";
+        let first = self.terminal_size.is_none();
+        self.terminal_size = Some(size);
+        if !self.fixed_layout {
+            self.layout = Layout::fit(size.0, size.1);
         }
-
-        if self.castling_rights[1][0] {
-            castle_string += "k";
+        if !first {
+            self.redraw_screen();
         }
+    }
 
-        if self.castling_rights[1][1] {
-            castle_string += "q";
+    /// Clears the screen and draws everything on it again.
+    fn redraw_screen(&mut self) {
+        write!(self.stdout, "{}", termion::clear::All).unwrap();
+        self.print_board();
+        if !self.moves.is_empty() {
+            self.highlight_square(self.selected_piece[0], self.selected_piece[1]);
+            self.highlight_moves();
         }
-
-        if castle_string.is_empty() {
-            castle_string += "-";
+        self.draw_clocks();
+        let sans = self.record.sans();
+        self.show_move_list(&sans, self.move_list_ply);
+        for line in Line::ALL {
+            let text = self.lines[line as usize].clone();
+            self.write_line(line, text);
         }
-
-        fen += &castle_string;
-
-        if self.en_passant.is_empty() {
-            fen += " -";
-        } else {
-            let en_passant_y = if self.turn == 0 {
-                self.en_passant[0][1] + 4
-            } else {
-                self.en_passant[0][1] - 2
-            };
-            fen += &format!(
-                " {}{}",
-                &char::from_u32(self.en_passant[0][0] as u32 + 97)
-                    .unwrap()
-                    .to_string(),
-                &en_passant_y.to_string(),
-            );
-        }
-
-        fen += &format!(
-            " {} {}",
-            &self.halfmove_clock.to_string(),
-            &self.fullmoves.to_string()
-        );
-
-        fen
+        (self.cursor_x, self.cursor_y) = self.screen_coords(self.x, self.y);
+        self.reset_cursor();
     }
 
-    fn display_fen_string(&mut self) {
-        let fen = self.create_fen_string();
+    /// Writes `text` on one of the lines under the board in place of what was there.
+    fn write_line(&mut self, line: Line, text: String) {
         write!(
             self.stdout,
-            "{}{}{}{}{}",
-            termion::cursor::Goto(1, 12),
-            termion::clear::AfterCursor,
-            color::Bg(color::Green),
-            fen,
+            "{}{}{}{}",
+            termion::cursor::Goto(1, self.layout.line_row(line as u16)),
+            termion::clear::CurrentLine,
+            text,
             style::Reset
         )
         .unwrap();
+        self.lines[line as usize] = text;
+        self.reset_cursor();
+    }
+
+    /// Shows the keys that can be pressed, clearing the lines under it.
+    fn show_hint(&mut self, hint: &str) {
+        for line in &Line::ALL[1..] {
+            self.write_line(*line, String::new());
+        }
+        self.write_line(
+            Line::Hint,
+            format!("{}{}{}", self.alert_color(), hint, style::Reset),
+        );
+    }
+
+    /// Turns the board round, keeping the cursor and any highlighted moves on their squares.
+    fn flip_board(&mut self) {
+        self.flipped = !self.flipped;
+        self.print_board();
+        if !self.moves.is_empty() {
+            self.highlight_square(self.selected_piece[0], self.selected_piece[1]);
+            self.highlight_moves();
+        }
+        self.draw_clocks();
+        (self.cursor_x, self.cursor_y) = self.screen_coords(self.x, self.y);
         self.reset_cursor();
     }
 
+    /// With auto-flip on, turns the board so the side to move is at the bottom.
+    fn auto_flip(&mut self) {
+        if self.auto_flip && self.flipped != (self.position.turn == 1) {
+            self.flip_board();
+        }
+    }
+
+    //FEN helper functions
+    fn display_fen_string(&mut self) {
+        let fen = self.position.to_fen();
+        let background = if self.palette == Palette::NoColor {
+            String::new()
+        } else {
+            color::Bg(color::Green).to_string()
+        };
+        self.write_line(Line::Fen, format!("{}{}", background, fen));
+    }
+
     fn copy_fen_to_clipboard(&mut self) {
         let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-        ctx.set_contents(self.create_fen_string()).unwrap();
-        write!(
-            self.stdout,
-            "{}Copied FEN string to clipboard!",
-            termion::cursor::Goto(1, 13)
-        )
-        .unwrap();
-        self.stdout.flush().unwrap();
+        ctx.set_contents(self.position.to_fen()).unwrap();
+        self.display_message("Copied FEN string to clipboard!");
     }
 
-    fn fill_board_from_fen_string(&mut self, fen: String) {
-        let contents: Vec<&str> = fen.split_whitespace().collect();
-        let pieces = contents[0];
-        let color = contents[1];
-        let castling_rights = contents[2];
-        let en_passant = contents[3];
-        let halfmove_clock = contents[4];
-        let fullmoves = contents[5];
-
-        let lines: Vec<&str> = pieces.split('/').collect();
-        for (y, line) in lines.into_iter().enumerate() {
-            let mut x = 0;
-            for c in line.chars() {
-                let piece: Piece = if c.is_ascii_digit() {
-                    Piece::Empty
-                } else {
-                    {
-                        match c.to_ascii_lowercase() {
-                            'k' => Piece::King,
-                            'q' => Piece::Queen,
-                            'r' => Piece::Rook,
-                            'b' => Piece::Bishop,
-                            'n' => Piece::Knight,
-                            'p' => Piece::Pawn,
-                            _ => Piece::Empty,
-                        }
-                    }
-                };
+    fn display_message(&mut self, message: &str) {
+        self.write_line(Line::Message, message.to_string());
+    }
 
-                if piece == Piece::Empty {
-                    let i: usize = c.to_digit(10).unwrap() as usize;
-                    for _ in 0..i {
-                        self.place_piece(Piece::Empty, 2, x, y);
-                        x += 1;
-                    }
-                } else {
-                    let color: usize = if c.is_ascii_uppercase() { 0 } else { 1 };
+    //PGN helper functions
+    fn save_pgn(&mut self) {
+        let message = match fs::write(&self.pgn_path, self.record.to_pgn()) {
+            Ok(()) => format!("Saved game to {}", self.pgn_path),
+            Err(e) => format!("Could not save {}: {}", self.pgn_path, e),
+        };
+        self.display_message(&message);
+    }
 
-                    if piece == Piece::King {
-                        self.king_coords[color] = [x, y];
-                    }
-                    self.place_piece(piece, color, x, y);
-                    x += 1;
-                }
-            }
+    fn copy_pgn_to_clipboard(&mut self) {
+        let copied = ClipboardProvider::new()
+            .and_then(|mut ctx: ClipboardContext| ctx.set_contents(self.record.to_pgn()));
+        match copied {
+            Ok(()) => self.display_message("Copied PGN to clipboard!"),
+            Err(_) => self.display_message("Could not access the clipboard"),
         }
+    }
 
-        if color.starts_with('w') {
-            self.turn = 0;
-        } else {
-            self.turn = 1;
+    // Gameplay helper functions
+    fn play_move(&mut self, m: &ChessMove) {
+        let mover = self.position.turn;
+        let thinking = self.thinking();
+        let mut remaining = None;
+        if let Some(clock) = self.clock.as_mut() {
+            if !clock.press(mover, thinking) {
+                self.flag_fell(mover);
+                return;
+            }
+            remaining = Some(clock.remaining(mover));
         }
+        self.move_started = Some(Instant::now());
+        // Playing on turns down a draw offer
+        self.draw_offer = None;
 
-        self.castling_rights = [[false, false], [false, false]];
-        for c in castling_rights.chars() {
-            match c {
-                'K' => self.castling_rights[0][0] = true,
-                'Q' => self.castling_rights[0][1] = true,
-                'k' => self.castling_rights[1][0] = true,
-                'q' => self.castling_rights[1][1] = true,
-                '-' => break,
-                _ => (),
-            }
+        self.record.push(*m);
+        if let Some(remaining) = remaining {
+            self.record.set_clock(remaining);
         }
+        self.position.make_move(m);
+        self.mark_last_move(self.record.moves().len());
+        self.redraw_board();
+        self.auto_flip();
+        let sans = self.record.sans();
+        self.show_move_list(&sans, sans.len());
+        self.reset_cursor();
+        self.check_for_mate();
+        if self.show_fen {
+            self.display_fen_string();
+        }
+        self.analyse(self.record.moves().len());
+    }
 
-        let en_p_chars: Vec<char> = en_passant.chars().collect();
-        if en_p_chars[0] != '-' {
-            let x = en_p_chars[0].to_ascii_uppercase() as usize - 65;
-            let mut y = en_p_chars[1].to_digit(10).unwrap() as usize;
-            if self.turn == 0 {
-                y -= 4;
-            } else {
-                y += 2;
-            }
-            self.en_passant.clear();
-            self.en_passant.push([x, y]);
+    /// Lets the computer search for and play a reply when it is its turn.
+    fn play_computer_move(&mut self) {
+        let Some(computer) = self.computer else {
+            return;
+        };
+        if self.position.turn != computer.color || self.record.result() != GameResult::Ongoing {
+            return;
         }
 
-        self.halfmove_clock = halfmove_clock.parse::<usize>().unwrap();
-        self.fullmoves = fullmoves.parse::<usize>().unwrap();
+        self.write_line(Line::Status, "Thinking...".to_string());
+        let limit = match (computer.limit, &self.clock) {
+            (Some(limit), _) => limit,
+            (None, Some(clock)) => Limit::Time(clock.time_for_move(computer.color)),
+            (None, None) => Limit::Time(Duration::from_secs(1)),
+        };
+        let m = if self.engine.is_some() {
+            self.engine_move(limit)
+        } else {
+            search(&self.position, limit).best_move()
+        };
+        if let Some(m) = m {
+            self.play_move(&m);
+        }
     }
 
-    // Valid move finder helper functions
-    fn check_for_pin(&mut self) -> Option<[Move; 2]> {
-        let x = self.selected_piece[0] as isize;
-        let y = self.selected_piece[1] as isize;
-        let king_x = self.king_coords[self.turn][0] as isize;
-        let king_y = self.king_coords[self.turn][1] as isize;
-        let moves;
-
-        if x == king_x {
-            if y > king_y {
-                moves = [Move::U, Move::D];
-            } else {
-                moves = [Move::D, Move::U];
-            }
-        } else if y == king_y {
-            if x > king_x {
-                moves = [Move::L, Move::R];
-            } else {
-                moves = [Move::R, Move::L];
-            }
-        } else if (y - king_y).abs() == (x - king_x).abs() {
-            if y > king_y && x > king_x {
-                moves = [Move::UL, Move::DR];
-            } else if y > king_y && x < king_x {
-                moves = [Move::UR, Move::DL];
-            } else if y < king_y && x > king_x {
-                moves = [Move::DL, Move::UR];
-            } else {
-                moves = [Move::DR, Move::UL];
+    /// Starts a new record from the current position, for example after editing the board.
+    fn reset_record(&mut self) {
+        let time_control = self.clock.as_ref().map(|clock| clock.control);
+        self.record = new_record(
+            &self.position,
+            self.computer,
+            self.engine.as_ref(),
+            time_control,
+        );
+        self.reset_clock();
+        self.mark_last_move(0);
+        self.redraw_board();
+        if let Some(engine) = self.engine.as_mut() {
+            if let Err(e) = engine.new_game() {
+                self.engine_failed(e);
             }
-        } else {
-            return None;
         }
+    }
 
-        let mut change = get_change_from_move(&moves[0]);
-        let mut tmp_x: isize = x;
-        let mut tmp_y: isize = y;
+    // Engine helper functions
+    /// Waits for the next key or mouse event, showing engine output in the meantime.
+    fn next_event(&mut self) -> Event {
         loop {
-            tmp_x += change[0];
-            tmp_y += change[1];
+            if let Some(event) = self.stdin.next() {
+                return event.unwrap();
+            }
+            self.fit_to_terminal();
+            self.poll_engine();
+            self.poll_network();
+            self.tick_clock();
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
 
-            if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
-                return None;
+    // Network helper functions
+    /// Plays a move made on this terminal, passing it on to the other player or letting
+    /// the computer answer it.
+    fn play_own_move(&mut self, m: &ChessMove) {
+        if let Some(network) = self.network.as_mut() {
+            if let Err(e) = network.send_move(&self.position, m) {
+                self.network_failed(e);
+                return;
             }
+        }
+        self.play_move(m);
+        self.play_computer_move();
+    }
 
-            match self.board[tmp_y as usize][tmp_x as usize].piece {
-                Piece::King => {
-                    if self.board[tmp_y as usize][tmp_x as usize].color != self.turn {
-                        return None;
-                    } else {
-                        break;
-                    }
-                }
-                Piece::Empty => continue,
-                _ => return None,
+    fn network_failed(&mut self, e: NetError) {
+        self.network = None;
+        self.display_message(&format!("Network game over: {}", e));
+    }
+
+    fn send_to_network(&mut self, message: Message) {
+        if let Some(network) = self.network.as_mut() {
+            if let Err(e) = network.send(&message) {
+                self.network_failed(e);
             }
         }
+    }
 
-        change = get_change_from_move(&moves[1]);
-        tmp_x = x;
-        tmp_y = y;
-        loop {
-            tmp_x += change[0];
-            tmp_y += change[1];
+    /// Carries out whatever the other player did since the last poll.
+    fn poll_network(&mut self) {
+        let Some(network) = self.network.as_mut() else {
+            return;
+        };
+        let event = match network.poll(&self.position) {
+            Ok(Some(event)) => event,
+            Ok(None) => return,
+            Err(e) => return self.network_failed(e),
+        };
+        let opponent = 1 - network.color;
+        if self.record.result() != GameResult::Ongoing {
+            // Anything sent as the game ended is moot
+            return;
+        }
 
-            if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
-                return None;
+        match event {
+            NetEvent::Move(m) => {
+                self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
+                self.unhighlight_moves();
+                self.play_move(&m);
             }
-            if self.board[tmp_y as usize][tmp_x as usize].color == self.turn {
-                return None;
+            NetEvent::OfferDraw => {
+                self.draw_offer = Some(opponent);
+                self.display_message("Your opponent offers a draw, a:Accept");
             }
-
-            match self.board[tmp_y as usize][tmp_x as usize].piece {
-                Piece::Queen | Piece::Bishop | Piece::Rook => {
-                    if self.board[tmp_y as usize][tmp_x as usize]
-                        .moves
-                        .contains(&moves[0])
-                    {
-                        return Some(moves);
-                    } else {
-                        return None;
-                    }
-                }
-                _ => (),
+            NetEvent::AcceptDraw if self.draw_offer == Some(1 - opponent) => self.agree_draw(),
+            NetEvent::AcceptDraw => {
+                self.network_failed(NetError::Protocol(
+                    "accepted a draw that was not offered".to_string(),
+                ));
+            }
+            NetEvent::DeclineDraw => {
+                self.draw_offer = None;
+                self.display_message("Your opponent declined the draw");
+            }
+            NetEvent::ClaimDraw if self.record.claimable_draw().is_some() => self.end_by_claim(),
+            NetEvent::ClaimDraw => {
+                self.network_failed(NetError::Protocol(
+                    "claimed a draw that cannot be claimed".to_string(),
+                ));
+            }
+            NetEvent::Resign => {
+                self.record.resign(opponent);
+                self.check_for_mate();
             }
         }
     }
 
-    fn is_attacked(&mut self, x: isize, y: isize) -> bool {
-        let dirs: Vec<Move> = Move::iter().collect();
-        let attacked: Vec<bool> = dirs
-            .into_par_iter()
-            .filter_map(|dir| {
-                let change = get_change_from_move(&dir);
-                let mut tmp_x: isize = x;
-                let mut tmp_y: isize = y;
-
-                for i in 0..7 {
-                    tmp_x += change[0];
-                    tmp_y += change[1];
-                    if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
-                        return None;
-                    }
+    fn offer_draw(&mut self) {
+        if self.network.is_none()
+            || self.draw_offer.is_some()
+            || self.record.result() != GameResult::Ongoing
+        {
+            return;
+        }
+        self.draw_offer = self.network_color;
+        self.send_to_network(Message::OfferDraw);
+        self.display_message("Draw offered");
+    }
 
-                    if self.board[tmp_y as usize][tmp_x as usize].color == self.turn {
-                        return None;
-                    }
+    /// Answers the other player's draw offer.
+    fn answer_draw(&mut self, accept: bool) {
+        if self.draw_offer.is_none() || self.draw_offer == self.network_color {
+            return;
+        }
+        if accept {
+            self.send_to_network(Message::AcceptDraw);
+            self.agree_draw();
+        } else {
+            self.draw_offer = None;
+            self.send_to_network(Message::DeclineDraw);
+            self.display_message("");
+        }
+    }
 
-                    match self.board[tmp_y as usize][tmp_x as usize].piece {
-                        Piece::Empty => (),
-                        Piece::King | Piece::Knight => {
-                            if i > 0 {
-                                return None;
-                            }
-
-                            if self.board[tmp_y as usize][tmp_x as usize]
-                                .moves
-                                .contains(&dir)
-                            {
-                                return Some(true);
-                            } else {
-                                return None;
-                            }
-                        }
-                        Piece::Pawn => {
-                            if i > 0 {
-                                return None;
-                            }
-
-                            if self.turn == 0 {
-                                if dir == Move::UL || dir == Move::UR {
-                                    return Some(true);
-                                }
-                                return None;
-                            } else {
-                                if dir == Move::DL || dir == Move::DR {
-                                    return Some(true);
-                                }
-                                return None;
-                            }
-                        }
-                        _ => {
-                            if self.board[tmp_y as usize][tmp_x as usize]
-                                .moves
-                                .contains(&dir)
-                            {
-                                return Some(true);
-                            } else {
-                                return None;
-                            }
-                        }
-                    }
-                }
+    fn agree_draw(&mut self) {
+        self.draw_offer = None;
+        self.record.outcome = Some(GameResult::Draw);
+        self.display_message("");
+        self.check_for_mate();
+    }
 
-                None
-            })
-            .collect();
+    fn resign(&mut self) {
+        let Some(color) = self.network_color else {
+            return;
+        };
+        if self.network.is_none() || self.record.result() != GameResult::Ongoing {
+            return;
+        }
+        self.send_to_network(Message::Resign);
+        self.record.resign(color);
+        self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
+        self.unhighlight_moves();
+        self.check_for_mate();
+    }
 
-        !attacked.is_empty()
+    // Clock helper functions
+    /// Sets both clocks to the time left after each side's last move in the record, and
+    /// starts the side to move thinking afresh.
+    fn reset_clock(&mut self) {
+        let Some(clock) = self.clock.as_mut() else {
+            return;
+        };
+        let start_turn = self.record.start().turn;
+        for color in 0..2 {
+            let last = self
+                .record
+                .clocks()
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(ply, _)| (start_turn + ply) % 2 == color)
+                .and_then(|(_, remaining)| *remaining);
+            clock.set_remaining(color, last.unwrap_or(clock.control.base));
+        }
+        self.move_started = Some(Instant::now());
+        self.draw_clocks();
     }
 
-    fn filter_legal_moves(&mut self) {
-        let current_square = self.board[self.y][self.x].clone();
-        self.empty_square(self.x, self.y);
-        self.moves = self
-            .moves
-            .clone()
-            .into_iter()
-            .filter(|coords| {
-                if current_square.piece == Piece::King {
-                    self.king_coords[self.turn] = *coords;
-                }
-                let replaced_piece = self.board[coords[1]][coords[0]].clone();
-                self.board[coords[1]][coords[0]] = current_square.clone();
-                let check = self.is_attacked(
-                    self.king_coords[self.turn][0] as isize,
-                    self.king_coords[self.turn][1] as isize,
-                );
-
-                self.board[coords[1]][coords[0]] = replaced_piece;
-                if current_square.piece == Piece::King {
-                    self.king_coords[self.turn] = [self.x, self.y];
-                }
+    fn stop_clock(&mut self) {
+        self.move_started = None;
+        self.draw_clocks();
+    }
 
-                !check
-            })
-            .collect();
-        self.board[self.y][self.x] = current_square;
+    fn thinking(&self) -> Duration {
+        self.move_started
+            .map_or(Duration::ZERO, |started| started.elapsed())
     }
 
-    fn find_moves(&mut self) {
-        self.moves.clear();
-        match self.board[self.y][self.x].piece {
-            Piece::Pawn => {
-                let valid_moves: Vec<Move> = match self.check_for_pin() {
-                    Some(pin_moves) => pin_moves
-                        .into_iter()
-                        .filter(|m| self.board[self.y][self.x].moves.contains(m))
-                        .collect(),
-                    None => self.board[self.y][self.x].moves.clone(),
-                };
+    /// Ends the game on time once the flag of the side to move falls.
+    fn tick_clock(&mut self) {
+        let Some(clock) = &self.clock else {
+            return;
+        };
+        if self.game_over || self.move_started.is_none() {
+            return;
+        }
+        let turn = self.record.position().turn;
+        if clock.running(turn, self.thinking()).is_zero() {
+            self.flag_fell(turn);
+        } else {
+            self.draw_clocks();
+        }
+    }
 
-                self.moves = valid_moves
-                    .into_par_iter()
-                    .flat_map(|m| {
-                        let change = get_change_from_move(&m);
-                        let mut tmp_x: isize = self.x as isize;
-                        let mut tmp_y: isize = self.y as isize;
-                        let mut moves: Vec<[usize; 2]> = Vec::new();
-                        tmp_x += change[0];
-                        tmp_y += change[1];
-
-                        if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
-                            return moves;
-                        }
+    fn flag_fell(&mut self, color: usize) {
+        if let Some(clock) = self.clock.as_mut() {
+            clock.set_remaining(color, Duration::ZERO);
+        }
+        self.record.flag_fell(color);
+        self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
+        self.unhighlight_moves();
+        self.check_for_mate();
+        self.draw_clocks();
+    }
 
-                        if self.board[tmp_y as usize][tmp_x as usize].color == self.turn {
-                            return moves;
-                        }
+    /// Shows each side's time to the right of the board, Black's by the 8th rank and
+    /// White's by the 1st. The clock that is running is shown inverted.
+    fn draw_clocks(&mut self) {
+        let Some(clock) = &self.clock else {
+            return;
+        };
+        let turn = self.record.position().turn;
+        let (top, bottom) = self.layout.clock_rows();
+        for (color, name) in [(0, "White"), (1, "Black")] {
+            let row = if (color == 0) != self.flipped {
+                bottom
+            } else {
+                top
+            };
+            let running = color == turn && !self.game_over && self.move_started.is_some();
+            let time = if running {
+                clock.running(color, self.thinking())
+            } else {
+                clock.remaining(color)
+            };
+            let highlight = if running {
+                style::Invert.to_string()
+            } else {
+                String::new()
+            };
+            write!(
+                self.stdout,
+                "{}{} {} {:>8} {}",
+                termion::cursor::Goto(self.layout.clock_column(), row),
+                highlight,
+                name,
+                format_clock(time),
+                style::Reset
+            )
+            .unwrap();
+        }
+        self.reset_cursor();
+    }
 
-                        match m {
-                            Move::U | Move::D => {
-                                if self.board[tmp_y as usize][tmp_x as usize].piece == Piece::Empty
-                                {
-                                    moves.push([tmp_x as usize, tmp_y as usize]);
-                                }
-
-                                if (self.turn == 0 && self.y == 6)
-                                    || (self.turn == 1 && self.y == 1)
-                                {
-                                    tmp_y += change[1];
-                                } else {
-                                    return moves;
-                                }
-
-                                if self.board[tmp_y as usize][tmp_x as usize].piece == Piece::Empty
-                                {
-                                    moves.push([tmp_x as usize, tmp_y as usize]);
-                                }
-                            }
-                            _ => {
-                                if self.board[tmp_y as usize][tmp_x as usize].piece != Piece::Empty
-                                {
-                                    moves.push([tmp_x as usize, tmp_y as usize]);
-                                    return moves;
-                                }
-
-                                if !self.en_passant.is_empty()
-                                    && self.en_passant[0][0] == tmp_x as usize
-                                    && self.en_passant[0][1] == tmp_y as usize
-                                {
-                                    moves.push([tmp_x as usize, tmp_y as usize]);
-                                }
-                            }
-                        }
-                        moves
-                    })
-                    .collect();
+    fn engine_failed(&mut self, e: EngineError) {
+        self.engine = None;
+        self.analysis = None;
+        self.display_message(&format!("Engine stopped: {}", e));
+    }
 
-                if self.king_in_check {
-                    self.filter_legal_moves();
+    /// Asks the engine for its move in the current game.
+    fn engine_move(&mut self, limit: Limit) -> Option<ChessMove> {
+        let engine = self.engine.as_mut()?;
+        let uci = engine
+            .set_position(self.record.start(), self.record.moves())
+            .and_then(|_| engine.go(limit))
+            .and_then(|_| engine.best_move());
+        match uci {
+            Ok(uci) => {
+                let m = self.position.parse_uci_move(&uci);
+                if m.is_none() {
+                    self.display_message(&format!("The engine played an illegal move: {}", uci));
                 }
+                m
             }
-            Piece::King => {
-                self.moves = self.board[self.y][self.x]
-                    .moves
-                    .par_iter()
-                    .filter_map(|m| {
-                        let change = get_change_from_move(m);
-                        let mut tmp_x: isize = self.x as isize;
-                        let mut tmp_y: isize = self.y as isize;
-                        tmp_x += change[0];
-                        tmp_y += change[1];
-
-                        if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
-                            return None;
-                        }
-
-                        if self.board[tmp_y as usize][tmp_x as usize].color == self.turn {
-                            return None;
-                        }
-
-                        Some([tmp_x as usize, tmp_y as usize])
-                    })
-                    .collect();
-                self.filter_legal_moves();
-                if self.castling_rights[self.turn][0] {
-                    let mut castle = true;
-                    let mut tmp_x = self.x;
-                    for _ in 0..2 {
-                        tmp_x += 1;
-                        if self.board[self.y][tmp_x].piece != Piece::Empty {
-                            castle = false;
-                            break;
-                        }
-
-                        if self.is_attacked(tmp_x as isize, self.y as isize) {
-                            castle = false;
-                            break;
-                        }
-                    }
-
-                    if castle {
-                        self.moves.push([self.x + 2, self.y]);
-                    }
-                }
-
-                if self.castling_rights[self.turn][1] {
-                    let mut castle = true;
-                    let mut tmp_x = self.x;
-
-                    for _ in 0..3 {
-                        tmp_x -= 1;
-                        if self.board[self.y][tmp_x].piece != Piece::Empty {
-                            castle = false;
-                            break;
-                        }
-
-                        if self.is_attacked(tmp_x as isize, self.y as isize) {
-                            castle = false;
-                            break;
-                        }
-                    }
-
-                    if castle {
-                        self.moves.push([self.x - 2, self.y]);
-                    }
-                }
+            Err(e) => {
+                self.engine_failed(e);
+                None
             }
-            Piece::Knight => {
-                if !matches!(self.check_for_pin(), None) {
-                    return;
-                }
-
-                self.moves = self.board[self.y][self.x]
-                    .moves
-                    .par_iter()
-                    .filter_map(|m| {
-                        let change = get_change_from_move(m);
-                        let mut tmp_x: isize = self.x as isize;
-                        let mut tmp_y: isize = self.y as isize;
-                        tmp_x += change[0];
-                        tmp_y += change[1];
-
-                        if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
-                            return None;
-                        }
+        }
+    }
 
-                        if self.board[tmp_y as usize][tmp_x as usize].color == self.turn {
-                            return None;
-                        }
+    /// Has the engine analyse the position after the first `ply` moves until the position
+    /// changes again. Does nothing when the engine is playing instead.
+    fn analyse(&mut self, ply: usize) {
+        if self.computer.is_some() {
+            return;
+        }
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+
+        let moves = &self.record.moves()[..ply];
+        let started = engine
+            .stop()
+            .and_then(|_| engine.set_position(self.record.start(), moves))
+            .and_then(|_| engine.go(Limit::Infinite));
+        match started {
+            Ok(()) => self.analysis = Some(self.record.position_at(ply)),
+            Err(e) => self.engine_failed(e),
+        }
+    }
 
-                        Some([tmp_x as usize, tmp_y as usize])
-                    })
-                    .collect();
-                if self.king_in_check {
-                    self.filter_legal_moves();
-                }
+    fn stop_analysis(&mut self) {
+        if let Some(engine) = self.engine.as_mut() {
+            if let Err(e) = engine.stop() {
+                self.engine_failed(e);
             }
-            _ => {
-                let valid_moves: Vec<Move> = match self.check_for_pin() {
-                    Some(pin_moves) => pin_moves
-                        .into_iter()
-                        .filter(|m| self.board[self.y][self.x].moves.contains(m))
-                        .collect(),
-                    None => self.board[self.y][self.x].moves.clone(),
-                };
-                self.moves = valid_moves
-                    .into_par_iter()
-                    .flat_map(|m| {
-                        let change = get_change_from_move(&m);
-                        let mut tmp_x: isize = self.x as isize;
-                        let mut tmp_y: isize = self.y as isize;
-                        let mut moves: Vec<[usize; 2]> = Vec::new();
-                        for _ in 0..7 {
-                            tmp_x += change[0];
-                            tmp_y += change[1];
-
-                            if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
-                                break;
-                            }
-
-                            if self.board[tmp_y as usize][tmp_x as usize].color == self.turn {
-                                break;
-                            }
-
-                            match self.board[tmp_y as usize][tmp_x as usize].piece {
-                                Piece::Empty => moves.push([tmp_x as usize, tmp_y as usize]),
-                                _ => {
-                                    moves.push([tmp_x as usize, tmp_y as usize]);
-                                    break;
-                                }
-                            }
-                        }
+        }
+        self.analysis = None;
+        self.write_line(Line::Analysis, String::new());
+    }
 
-                        moves
-                    })
-                    .collect();
-                if self.king_in_check {
-                    self.filter_legal_moves();
-                }
+    /// Shows whatever the engine reported since the last poll.
+    fn poll_engine(&mut self) {
+        while let Some(engine) = self.engine.as_mut() {
+            match engine.poll() {
+                Ok(Some(EngineEvent::Info(info))) => self.show_analysis(&info),
+                Ok(Some(EngineEvent::BestMove(_))) => (),
+                Ok(None) => return,
+                Err(e) => self.engine_failed(e),
             }
         }
     }
 
-    // Gameplay helper functions
-    fn update_en_passant_capture(&mut self) {
-        if self.en_passant.is_empty() {
+    /// Writes the score from White's side, the depth and the principal variation in SAN
+    /// under the board.
+    fn show_analysis(&mut self, info: &EngineInfo) {
+        let Some(mut position) = self.analysis.clone() else {
             return;
-        }
-
-        let sel_x = self.selected_piece[0];
-        let sel_y = self.selected_piece[1];
-
-        if self.board[sel_y][sel_x].piece != Piece::Pawn {
+        };
+        let (Some(depth), Some(score)) = (info.depth, info.score) else {
             return;
-        }
-
-        if self.x != self.en_passant[0][0] || self.y != self.en_passant[0][1] {
+        };
+        if info.pv.is_empty() {
             return;
         }
 
-        if self.turn == 0 {
-            self.place_piece(Piece::Empty, 2, self.x, self.y + 1);
-        } else {
-            self.place_piece(Piece::Empty, 2, self.x, self.y - 1);
+        let sign = if position.turn == 0 { 1 } else { -1 };
+        let mut text = match score {
+            Score::Centipawns(cp) => format!("{:+.2}", (sign * cp) as f64 / 100.0),
+            Score::Mate(moves) => format!("#{}", sign * moves),
+        };
+        text += &format!(" depth {}:", depth);
+        for (i, uci) in info.pv.iter().enumerate() {
+            let Some(m) = position.parse_uci_move(uci) else {
+                break;
+            };
+            if position.turn == 0 {
+                text += &format!(" {}.", position.fullmoves);
+            } else if i == 0 {
+                text += &format!(" {}...", position.fullmoves);
+            }
+            text += &format!(" {}", position.san(&m));
+            position.make_move(&m);
         }
+        text.truncate(text.char_indices().nth(80).map_or(text.len(), |(i, _)| i));
+        self.write_line(Line::Analysis, text);
     }
 
-    fn castle_king(&mut self) {
-        let sel_x = self.selected_piece[0];
-        let sel_y = self.selected_piece[1];
+    /// Whether the side to move is played by the computer.
+    fn computer_to_move(&self) -> bool {
+        self.computer
+            .is_some_and(|computer| computer.color == self.record.position().turn)
+    }
 
-        if self.board[sel_y][sel_x].piece != Piece::King {
+    fn claim_draw(&mut self) {
+        // Over the network only the side to move may claim
+        if self
+            .network_color
+            .is_some_and(|color| color != self.record.position().turn)
+        {
             return;
         }
-
-        if (sel_x as isize - self.x as isize).abs() != 2 {
-            return;
+        if self.record.claimable_draw().is_some() {
+            self.send_to_network(Message::ClaimDraw);
+            self.end_by_claim();
         }
+    }
 
-        if self.x > sel_x {
-            self.place_piece(Piece::Rook, self.turn, self.x - 1, self.y);
-            self.place_piece(Piece::Empty, 2, 7, self.y);
-        } else {
-            self.place_piece(Piece::Rook, self.turn, self.x + 1, self.y);
-            self.place_piece(Piece::Empty, 2, 0, self.y);
-        }
+    fn end_by_claim(&mut self) {
+        self.draw_offer = None;
+        self.record.outcome = Some(GameResult::Draw);
+        self.game_over = true;
+        self.write_line(
+            Line::Status,
+            format!("{}Draw claimed!{}", self.alert_color(), style::Reset),
+        );
     }
 
-    fn should_promote_pawn(&mut self) -> bool {
-        if self.board[self.y][self.x].piece != Piece::Pawn {
-            return false;
+    fn undo_move(&mut self) {
+        if self.record.undo().is_some() {
+            // Take back the computer's reply together with the move it answered
+            if self.computer_to_move() {
+                self.record.undo();
+            }
+            self.show_record_position();
+            self.play_computer_move();
         }
+    }
 
-        if self.turn == 0 {
-            self.y == 0
-        } else {
-            self.y == 7
+    fn redo_move(&mut self) {
+        if self.record.redo().is_some() {
+            if self.computer_to_move() {
+                self.record.redo();
+            }
+            self.show_record_position();
+            self.play_computer_move();
         }
     }
 
-    fn move_selected_piece(&mut self) {
-        let to_move_x = self.selected_piece[0];
-        let to_move_y = self.selected_piece[1];
-        self.board[self.y][self.x] = self.board[to_move_y][to_move_x].clone();
-        self.empty_square(to_move_x, to_move_y);
-        self.update_square(to_move_x, to_move_y);
-        self.update_square(self.x, self.y);
+    /// Redraws the board after the record moved to another position.
+    fn show_record_position(&mut self) {
+        self.unhighlight_moves();
+        self.position = self.record.position();
+        self.reset_clock();
+        self.mark_last_move(self.record.moves().len());
+        self.redraw_board();
+        self.auto_flip();
+        let sans = self.record.sans();
+        self.show_move_list(&sans, sans.len());
+        self.reset_cursor();
+        self.check_for_mate();
+        if self.show_fen {
+            self.display_fen_string();
+        }
+        self.analyse(self.record.moves().len());
     }
 
     //Terminal output helper functions
+    /// Whether a move may be played on this terminal now.
+    fn can_move(&self) -> bool {
+        if self.record.result() != GameResult::Ongoing {
+            return false;
+        }
+        match self.network_color {
+            Some(color) => self.network.is_some() && self.position.turn == color,
+            None => true,
+        }
+    }
+
     fn handle_click_or_enter(&mut self, state: &mut KeyCaptureState) {
-        if self.board[self.y][self.x].is_valid_move {
-            self.update_halfmove_clock();
-            self.update_en_passant_capture();
-            self.update_en_passant_field();
-            self.update_castling_rights();
-            self.castle_king();
-            self.update_king_coords();
-            self.move_selected_piece();
-            self.unhighlight_moves();
+        if !self.can_move() {
+            return;
+        }
+
+        // The moves shown are the selected piece's, or the drops of the picked pocket piece
+        let chosen: Vec<ChessMove> = self
+            .moves
+            .iter()
+            .filter(|m| m.to == [self.x, self.y])
+            .copied()
+            .collect();
+
+        if chosen.len() > 1 {
+            // Several moves to one square only happens when promoting
             self.reset_cursor();
-            if self.should_promote_pawn() {
-                *state = KeyCaptureState::PromotePawn;
-                return;
-            }
-            self.update_turn();
-            self.king_in_check = self.is_attacked(
-                self.king_coords[self.turn][0] as isize,
-                self.king_coords[self.turn][1] as isize,
-            );
-            self.check_for_mate();
-            if self.show_fen {
-                self.display_fen_string();
-            }
-        } else if matches!(self.board[self.y][self.x].piece, Piece::Empty)
-            || self.board[self.y][self.x].color != self.turn
-        {
+            *state = KeyCaptureState::PromotePawn;
+        } else if let Some(m) = chosen.first() {
+            self.unhighlight_moves();
+            self.play_own_move(m);
+        } else if self.position.square(self.x, self.y).color != self.position.turn {
             self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
             self.unhighlight_moves();
             self.reset_cursor();
         } else {
             self.select_piece();
             self.unhighlight_moves();
-            self.find_moves();
+            self.moves = self.position.moves_from(self.x, self.y);
             self.highlight_moves();
             self.reset_cursor();
         }
     }
+
     fn update_square(&mut self, x: usize, y: usize) {
-        write!(
-            self.stdout,
-            "{}{}{}{}",
-            termion::cursor::Goto((x + 2) as u16, (y + 1) as u16),
-            self.get_bg_color(x as u16, y as u16),
-            self.board[y][x].icon,
-            style::Reset
-        )
-        .unwrap();
+        self.draw_square(x, y, self.get_bg_color(x as u16, y as u16));
+    }
+
+    /// Fills the square at `x`, `y` with `background`, its piece in the middle.
+    fn draw_square(&mut self, x: usize, y: usize, background: String) {
+        let (width, height) = (self.layout.square_width, self.layout.square_height);
+        let (col, row) = self.board_coords(x, y);
+        let (left, top) = self.layout.square_origin(col, row);
+        let middle = format!(
+            "{}{}{}",
+            " ".repeat((width / 2) as usize),
+            self.icon(x, y),
+            " ".repeat((width - width / 2 - 1) as usize)
+        );
+        for line in 0..height {
+            let text = if line == height / 2 {
+                middle.clone()
+            } else {
+                " ".repeat(width as usize)
+            };
+            write!(
+                self.stdout,
+                "{}{}{}{}",
+                termion::cursor::Goto(left, top + line),
+                background,
+                text,
+                style::Reset
+            )
+            .unwrap();
+        }
+    }
+
+    fn redraw_board(&mut self) {
+        for y in 0..8 {
+            for x in 0..8 {
+                self.update_square(x, y);
+            }
+        }
+        self.draw_pockets();
+    }
+
+    /// Shows the pieces each side holds in Crazyhouse by its clock, with the piece picked
+    /// to drop inverted.
+    fn draw_pockets(&mut self) {
+        if !self.position.variant.has_drops() {
+            return;
+        }
+        let (top, bottom) = self.layout.pocket_rows();
+        for color in 0..2 {
+            let row = if (color == 0) != self.flipped {
+                bottom
+            } else {
+                top
+            };
+            for (slot, piece) in POCKET_PIECES.into_iter().enumerate() {
+                let picked = color == self.position.turn && self.drop_piece == Some(piece);
+                let highlight = if picked {
+                    style::Invert.to_string()
+                } else {
+                    String::new()
+                };
+                write!(
+                    self.stdout,
+                    "{}{}{}{:<2}{}",
+                    termion::cursor::Goto(self.layout.pocket_slot_column(slot), row),
+                    highlight,
+                    get_icon(&Square::new(piece, color), self.pieces),
+                    self.position.pocket(color, piece),
+                    style::Reset
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    /// The colour and piece of the pocket slot covering screen column `x` and row `y`.
+    fn pocket_piece_at(&self, x: u16, y: u16) -> Option<(usize, Piece)> {
+        if !self.position.variant.has_drops() {
+            return None;
+        }
+        let (top, slot) = self.layout.pocket_slot_at(x, y, POCKET_PIECES.len())?;
+        let color = if top == self.flipped { 0 } else { 1 };
+        Some((color, POCKET_PIECES[slot]))
+    }
+
+    /// Picks `piece` from the pocket of the side to move, showing the squares it can be
+    /// dropped on for the next click or enter.
+    fn select_drop(&mut self, piece: Piece) {
+        if !self.can_move() {
+            return;
+        }
+        let drops = self.position.drops(piece);
+        if drops.is_empty() {
+            self.display_message("That piece cannot be dropped");
+            return;
+        }
+        self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
+        self.unhighlight_moves();
+        self.moves = drops;
+        self.drop_piece = Some(piece);
+        self.highlight_moves();
+        self.draw_pockets();
+        self.reset_cursor();
     }
 
     fn highlight_square(&mut self, x: usize, y: usize) {
-        write!(
-            self.stdout,
-            "{}{}{}{}",
-            termion::cursor::Goto(x as u16 + 2, y as u16 + 1),
-            color::Bg(color::Rgb(200, 100, 0)),
-            self.board[y][x].icon,
-            style::Reset,
-        )
-        .unwrap();
+        self.draw_square(x, y, self.highlight_color());
     }
 
     fn unhighlight_square(&mut self, x: usize, y: usize) {
-        write!(
-            self.stdout,
-            "{}{}{}{}",
-            termion::cursor::Goto(x as u16 + 2, y as u16 + 1),
-            self.get_bg_color(x as u16, y as u16),
-            self.board[y][x].icon,
-            style::Reset,
-        )
-        .unwrap();
+        self.update_square(x, y);
     }
 
     fn highlight_moves(&mut self) {
         for m in self.moves.clone() {
-            self.highlight_square(m[0], m[1]);
-            self.board[m[1]][m[0]].is_valid_move = true;
+            self.highlight_square(m.to[0], m.to[1]);
         }
     }
 
     fn unhighlight_moves(&mut self) {
         for m in self.moves.clone() {
-            self.unhighlight_square(m[0], m[1]);
-            self.board[m[1]][m[0]].is_valid_move = false;
+            self.unhighlight_square(m.to[0], m.to[1]);
         }
         self.moves.clear();
+        if self.drop_piece.take().is_some() {
+            self.draw_pockets();
+        }
     }
 
     fn select_piece(&mut self) {
-        if matches!(self.board[self.y][self.x].piece, Piece::Empty) {
+        if self.position.square(self.x, self.y).piece == Piece::Empty {
             return;
         }
 
@@ -1131,7 +1393,7 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
     }
 
     fn place_piece(&mut self, p: Piece, color: usize, x: usize, y: usize) {
-        self.board[y][x] = Square::new(p, color);
+        self.position.place_piece(p, color, x, y);
         self.update_square(x, y);
     }
 
@@ -1139,251 +1401,343 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
         for y in 0..8 {
             for x in 0..8 {
                 self.empty_square(x, y);
-                self.update_square(x, y);
             }
         }
     }
 
-    fn empty_square(&mut self, x: usize, y: usize) {
-        if self.board[y][x].piece != Piece::Empty {
-            self.board[y][x] = Square::new(Piece::Empty, 2);
-        }
-    }
-
-    // Game data helper functions
-    fn update_turn(&mut self) {
-        if self.turn == 0 {
-            self.turn = 1;
-            self.fullmoves += 1;
-        } else {
-            self.turn = 0;
-        }
+    fn empty_square(&mut self, x: usize, y: usize) {
+        self.position.empty_square(x, y);
+        self.update_square(x, y);
     }
 
-    fn update_en_passant_field(&mut self) {
-        self.en_passant.clear();
-        let sel_x = self.selected_piece[0];
-        let sel_y = self.selected_piece[1];
+    fn check_for_mate(&mut self) {
+        self.game_over = self.record.result() != GameResult::Ongoing;
+        let flag_message;
+        let variant_message;
+        let variant = self.position.variant;
+        let message = match self.record.status() {
+            GameStatus::Ongoing if self.record.tag("Termination") == Some("time forfeit") => {
+                let (loser, winner) = if self.record.position().turn == 0 {
+                    ("White", "Black")
+                } else {
+                    ("Black", "White")
+                };
+                flag_message = if self.record.result() == GameResult::Draw {
+                    format!("{}'s flag fell, but {} cannot win: draw!", loser, winner)
+                } else {
+                    format!("{}'s flag fell, {} wins!", loser, winner)
+                };
+                &flag_message
+            }
+            GameStatus::Ongoing => match self.record.result() {
+                GameResult::WhiteWins => "Black resigned, White wins!",
+                GameResult::BlackWins => "White resigned, Black wins!",
+                GameResult::Draw if self.record.claimable_draw().is_some() => "Draw claimed!",
+                GameResult::Draw => "Draw agreed!",
+                GameResult::Ongoing => match self.record.claimable_draw() {
+                    Some(DrawClaim::ThreefoldRepetition) => "Threefold repetition, d:Claim draw",
+                    Some(DrawClaim::FiftyMoveRule) => "Fifty moves without progress, d:Claim draw",
+                    None => {
+                        self.write_line(Line::Status, self.variant_status());
+                        return;
+                    }
+                },
+            },
+            GameStatus::Checkmate => "Checkmate!",
+            GameStatus::Stalemate => "Stalemate!",
+            GameStatus::SeventyFiveMoveRule => "Draw by the 75 move rule!",
+            GameStatus::FivefoldRepetition => "Draw by fivefold repetition!",
+            GameStatus::InsufficientMaterial => "Draw by insufficient material!",
+            GameStatus::VariantWin(color) => {
+                let winner = if color == 0 { "White" } else { "Black" };
+                variant_message = format!("{} wins, {}!", winner, variant.win_reason());
+                &variant_message
+            }
+            GameStatus::VariantDraw => {
+                variant_message = format!("Draw, {}!", variant.draw_reason());
+                &variant_message
+            }
+        };
 
-        if self.board[sel_y][sel_x].piece != Piece::Pawn {
-            return;
-        }
+        self.write_line(
+            Line::Status,
+            format!("{}{}{}", self.alert_color(), message, style::Reset),
+        );
+    }
 
-        if (self.y as isize - sel_y as isize).abs() == 2 {
-            if self.turn == 0 {
-                self.en_passant.push([self.x, self.y + 1]);
-            } else {
-                self.en_passant.push([self.x, self.y - 1]);
+    /// The variant being played while the game goes on, with the checks given so far in
+    /// Three-check.
+    fn variant_status(&self) -> String {
+        let variant = self.position.variant;
+        match variant {
+            Variant::Standard => String::new(),
+            Variant::ThreeCheck => {
+                let [white, black] = self.position.checks;
+                format!(
+                    "{}: White has given {} of {} checks, Black {}",
+                    variant, white, CHECKS_TO_WIN, black
+                )
             }
+            _ => variant.to_string(),
         }
     }
 
-    fn update_castling_rights(&mut self) {
-        let sel_x = self.selected_piece[0];
-        let sel_y = self.selected_piece[1];
-        let square = &self.board[sel_y][sel_x];
-
-        if square.piece != Piece::King && square.piece != Piece::Rook {
-            return;
-        }
-
-        if square.piece == Piece::King {
-            self.castling_rights[self.turn] = [false, false];
-            return;
-        }
-
-        if sel_x == 7 && self.castling_rights[self.turn][0] {
-            self.castling_rights[self.turn][0] = false;
-        } else if sel_x == 0 && self.castling_rights[self.turn][1] {
-            self.castling_rights[self.turn][1] = false;
-        } else {
-            return;
+    // Move list functions
+    /// Shows the move list scrolled so the move `ply` plies into the game is in view, and
+    /// highlighted.
+    fn show_move_list(&mut self, sans: &[String], ply: usize) {
+        self.move_list_ply = ply;
+        if ply > 0 {
+            let row = self.move_list_row(ply);
+            if row < self.move_list_top {
+                self.move_list_top = row;
+            } else if row >= self.move_list_top + self.layout.move_list_rows() {
+                self.move_list_top = row + 1 - self.layout.move_list_rows();
+            }
         }
+        self.draw_move_list(sans);
     }
 
-    fn update_king_coords(&mut self) {
-        let sel_x = self.selected_piece[0];
-        let sel_y = self.selected_piece[1];
-
-        if self.board[sel_y][sel_x].piece != Piece::King {
-            return;
+    fn scroll_move_list(&mut self, up: bool) {
+        let sans = self.record.sans();
+        let rows = self.move_list_row(sans.len()) + 1;
+        if up {
+            self.move_list_top = self.move_list_top.saturating_sub(1);
+        } else if self.move_list_top + self.layout.move_list_rows() < rows {
+            self.move_list_top += 1;
         }
-
-        self.king_coords[self.turn] = [self.x, self.y];
+        self.draw_move_list(&sans);
     }
 
-    fn update_halfmove_clock(&mut self) {
-        let sel_x = self.selected_piece[0];
-        let sel_y = self.selected_piece[1];
-        if self.board[sel_y][sel_x].piece == Piece::Pawn
-            || self.board[self.y][self.x].piece != Piece::Empty
-        {
-            self.halfmove_clock = 0;
-        } else {
-            self.halfmove_clock += 1;
-        }
+    /// The row of the move list, counting from the first, that the move `ply` plies into the
+    /// game is on.
+    fn move_list_row(&self, ply: usize) -> usize {
+        (ply + self.record.start().turn).saturating_sub(1) / 2
     }
 
-    fn check_for_mate(&mut self) {
-        let cur_x = self.x;
-        let cur_y = self.y;
-        for y in 0..8 {
-            for x in 0..8 {
-                if self.board[y][x].color == self.turn {
-                    self.x = x;
-                    self.y = y;
-                    self.find_moves();
-                    if !self.moves.is_empty() {
-                        self.x = cur_x;
-                        self.y = cur_y;
-                        return;
-                    }
-                }
-            }
-        }
-
-        if self.king_in_check {
+    fn draw_move_list(&mut self, sans: &[String]) {
+        let start_turn = self.record.start().turn;
+        for i in 0..self.layout.move_list_rows() {
+            let row = self.move_list_top + i;
             write!(
                 self.stdout,
-                "{}{}Checkmate!{}",
-                termion::cursor::Goto(1, 11),
-                color::Bg(color::Red),
-                style::Reset
+                "{}{}",
+                termion::cursor::Goto(self.layout.move_list_column(), i as u16 + 1),
+                termion::clear::UntilNewline
             )
             .unwrap();
-        } else {
+            // Plies played after White's and Black's moves on this row
+            let plies = [2 * row + 1, 2 * row + 2].map(|ply| ply.checked_sub(start_turn));
+            let played =
+                |ply: &Option<usize>| ply.is_some_and(|ply| (1..=sans.len()).contains(&ply));
+            if !plies.iter().any(played) {
+                continue;
+            }
+
             write!(
                 self.stdout,
-                "{}{}Stalemate!{}",
-                termion::cursor::Goto(1, 11),
-                color::Bg(color::Red),
-                style::Reset
+                "{:>width$}",
+                format!("{}.", self.record.start().fullmoves + row),
+                width = MOVE_NUMBER_WIDTH as usize - 1
             )
             .unwrap();
+            for ply in plies {
+                let text = match ply {
+                    Some(0) | None => "...",
+                    Some(ply) if ply <= sans.len() => &sans[ply - 1],
+                    Some(_) => "",
+                };
+                let highlight = if ply == Some(self.move_list_ply) {
+                    style::Invert.to_string()
+                } else {
+                    String::new()
+                };
+                write!(self.stdout, " {}{}{}", highlight, text, style::Reset).unwrap();
+                write!(
+                    self.stdout,
+                    "{}",
+                    " ".repeat((MOVE_WIDTH as usize - 1).saturating_sub(text.len()))
+                )
+                .unwrap();
+            }
         }
-
-        self.x = cur_x;
-        self.y = cur_y;
         self.reset_cursor();
     }
 
+    /// The ply the move shown at screen column `x` and row `y` was played on, if there is
+    /// a move there.
+    fn move_list_ply_at(&self, x: u16, y: u16) -> Option<usize> {
+        let column = x.checked_sub(self.layout.move_list_column() + MOVE_NUMBER_WIDTH)?;
+        if !(1..=self.layout.move_list_rows() as u16).contains(&y) || column >= 2 * MOVE_WIDTH {
+            return None;
+        }
+        let row = self.move_list_top + y as usize - 1;
+        let side = (column / MOVE_WIDTH) as usize;
+        let ply = (2 * row + side + 1).checked_sub(self.record.start().turn)?;
+        (1..=self.record.moves().len())
+            .contains(&ply)
+            .then_some(ply)
+    }
+
     // Cursor Functions
+    /// Puts the cursor back on the board, or after the move being typed.
     fn reset_cursor(&mut self) {
-        write!(
-            self.stdout,
-            "{}",
-            termion::cursor::Goto(self.cursor_x, self.cursor_y)
-        )
-        .unwrap();
+        let (x, y) = match &self.input {
+            Some(input) => (
+                (INPUT_PROMPT.len() + input.chars().count() + 1) as u16,
+                self.layout.line_row(Line::Input as u16),
+            ),
+            None => (self.cursor_x, self.cursor_y),
+        };
+        write!(self.stdout, "{}", termion::cursor::Goto(x, y)).unwrap();
         self.stdout.flush().unwrap();
     }
 
+    /// Moves the cursor to the middle of the square covering screen column `x` and row
+    /// `y`, if one does.
     fn mouse_move_cursor(&mut self, x: u16, y: u16) {
-        if (2..=9).contains(&x) && (1..=8).contains(&y) {
-            self.x = (x - 2) as usize;
-            self.y = (y - 1) as usize;
-            self.cursor_x = x;
-            self.cursor_y = y;
+        if let Some((col, row)) = self.layout.square_at(x, y) {
+            let (col, row) = (col as usize, row as usize);
+            if self.flipped {
+                self.x = 7 - col;
+                self.y = 7 - row;
+            } else {
+                self.x = col;
+                self.y = row;
+            }
+            (self.cursor_x, self.cursor_y) = self.screen_coords(self.x, self.y);
             self.reset_cursor();
         }
     }
 
+    // The arrow keys move the cursor across the screen, whichever way round the board is
     fn left(&mut self) {
-        if self.x > 0 {
-            self.x -= 1;
-            self.cursor_x -= 1;
-            self.reset_cursor();
-        }
+        let x = self.cursor_x.saturating_sub(self.layout.square_width);
+        self.mouse_move_cursor(x, self.cursor_y);
     }
 
     fn right(&mut self) {
-        if self.x < 7 {
-            self.x += 1;
-            self.cursor_x += 1;
-            self.reset_cursor();
-        }
+        self.mouse_move_cursor(self.cursor_x + self.layout.square_width, self.cursor_y);
     }
 
     fn down(&mut self) {
-        if self.y < 7 {
-            self.y += 1;
-            self.cursor_y += 1;
-            self.reset_cursor();
-        }
+        self.mouse_move_cursor(self.cursor_x, self.cursor_y + self.layout.square_height);
     }
 
     fn up(&mut self) {
-        if self.y > 0 {
-            self.y -= 1;
-            self.cursor_y -= 1;
-            self.reset_cursor();
-        }
+        let y = self.cursor_y.saturating_sub(self.layout.square_height);
+        self.mouse_move_cursor(self.cursor_x, y);
     }
 
     //Keypress handlers
     fn handle_promote_pawn_event(&mut self, state: &mut KeyCaptureState) {
-        write!(
-            self.stdout,
-            "{}{}{}q:Queen r:Rook n:Knight b:Bishop{}",
-            termion::cursor::Goto(1, 10),
-            termion::clear::AfterCursor,
-            color::Bg(color::Red),
-            style::Reset
-        )
-        .unwrap();
-        self.reset_cursor();
-        loop {
-            let b = self.stdin.next().unwrap().unwrap();
+        // Antichess pawns may also become kings
+        let to_king = self.position.variant.promotions().contains(&Piece::King);
+        if to_king {
+            self.show_hint("q:Queen r:Rook n:Knight b:Bishop k:King ESC:Cancel");
+        } else {
+            self.show_hint("q:Queen r:Rook n:Knight b:Bishop ESC:Cancel");
+        }
+        let piece = loop {
+            let b = self.next_event();
             match b {
-                Event::Key(Key::Char('q')) => {
-                    self.place_piece(Piece::Queen, self.turn, self.x, self.y);
-                    break;
-                }
-                Event::Key(Key::Char('r')) => {
-                    self.place_piece(Piece::Rook, self.turn, self.x, self.y);
-                    break;
-                }
-                Event::Key(Key::Char('n')) => {
-                    self.place_piece(Piece::Knight, self.turn, self.x, self.y);
-                    break;
-                }
-                Event::Key(Key::Char('b')) => {
-                    self.place_piece(Piece::Bishop, self.turn, self.x, self.y);
-                    break;
+                Event::Key(Key::Char('q')) => break Piece::Queen,
+                Event::Key(Key::Char('r')) => break Piece::Rook,
+                Event::Key(Key::Char('n')) => break Piece::Knight,
+                Event::Key(Key::Char('b')) => break Piece::Bishop,
+                Event::Key(Key::Char('k')) if to_king => break Piece::King,
+                Event::Key(Key::Esc) => {
+                    self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
+                    self.unhighlight_moves();
+                    self.reset_cursor();
+                    *state = KeyCaptureState::Gameplay;
+                    return;
                 }
                 _ => (),
             }
+        };
+        *state = KeyCaptureState::Gameplay;
+        let promotion = self
+            .moves
+            .iter()
+            .find(|m| m.to == [self.x, self.y] && m.promotion == Some(piece))
+            .copied();
+        self.unhighlight_moves();
+        if let Some(m) = promotion {
+            self.play_own_move(&m);
         }
+    }
+
+    /// Picks a piece from the pocket by its letter, to then drop it on a square.
+    fn handle_choose_drop_event(&mut self, state: &mut KeyCaptureState) {
+        self.show_hint("p:Pawn n:Knight b:Bishop r:Rook q:Queen ESC:Cancel");
+        let piece = loop {
+            match self.next_event() {
+                Event::Key(Key::Esc) => break None,
+                Event::Key(Key::Char(c)) => {
+                    if let Some(piece) = piece_from_letter(c).filter(|p| POCKET_PIECES.contains(p))
+                    {
+                        break Some(piece);
+                    }
+                }
+                _ => (),
+            }
+        };
         *state = KeyCaptureState::Gameplay;
-        self.update_turn();
+        if let Some(piece) = piece {
+            self.select_drop(piece);
+        }
     }
 
     fn handle_gameplay_event(&mut self, state: &mut KeyCaptureState) {
-        write!(
-            self.stdout,
-            "{}{}{}q:Quit{}",
-            termion::cursor::Goto(1, 10),
-            termion::clear::AfterCursor,
-            color::Bg(color::Red),
-            style::Reset
-        )
-        .unwrap();
-        self.reset_cursor();
-        self.king_in_check = self.is_attacked(
-            self.king_coords[self.turn][0] as isize,
-            self.king_coords[self.turn][1] as isize,
-        );
+        // Moves cannot be taken back or the board edited behind the other player's back
+        let hint = if self.network_color.is_some() {
+            "q:Quit m:Type move s:Save PGN p:Copy PGN x:Flip o:Offer draw R:Resign"
+        } else {
+            "q:Quit m:Type move u:Undo y:Redo r:Replay s:Save PGN p:Copy PGN x:Flip"
+        };
+        if self.position.variant.has_drops() {
+            self.show_hint(&format!("{} @:Drop", hint));
+        } else {
+            self.show_hint(hint);
+        }
+        self.check_for_mate();
+        let sans = self.record.sans();
+        self.show_move_list(&sans, sans.len());
 
         if self.show_fen {
             self.display_fen_string();
         }
+        self.analyse(self.record.moves().len());
+        self.play_computer_move();
 
         loop {
-            let b = self.stdin.next().unwrap().unwrap();
+            let b = self.next_event();
             match b {
-                Event::Mouse(MouseEvent::Release(x, y)) => {
-                    self.mouse_move_cursor(x, y);
-                    self.handle_click_or_enter(state);
+                Event::Mouse(MouseEvent::Release(x, y)) if x >= self.layout.move_list_column() => {
+                    // Clicking a move steps back through the game to it
+                    if let Some(ply) = self.move_list_ply_at(x, y) {
+                        if self.network_color.is_none() {
+                            self.ply = ply;
+                            *state = KeyCaptureState::Replay;
+                            return;
+                        }
+                    }
+                }
+                Event::Mouse(MouseEvent::Release(x, y)) => match self.pocket_piece_at(x, y) {
+                    // Only the side to move may drop a piece
+                    Some((color, piece)) if color == self.position.turn => self.select_drop(piece),
+                    Some(_) => (),
+                    None => {
+                        self.mouse_move_cursor(x, y);
+                        self.handle_click_or_enter(state);
+                    }
+                },
+                Event::Mouse(MouseEvent::Press(MouseButton::WheelUp, _, _)) => {
+                    self.scroll_move_list(true)
+                }
+                Event::Mouse(MouseEvent::Press(MouseButton::WheelDown, _, _)) => {
+                    self.scroll_move_list(false)
                 }
                 Event::Key(Key::Left) => self.left(),
                 Event::Key(Key::Right) => self.right(),
@@ -1392,32 +1746,45 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
                 Event::Key(Key::Char('\n')) => {
                     self.handle_click_or_enter(state);
                 }
-                Event::Key(Key::Char('e')) => {
+                Event::Key(Key::Char('@')) if self.position.variant.has_drops() => {
+                    *state = KeyCaptureState::ChooseDrop;
+                    return;
+                }
+                Event::Key(Key::Char('e')) if self.network_color.is_none() => {
                     *state = KeyCaptureState::EditBoard;
                     return;
                 }
+                Event::Key(Key::Char('m')) => {
+                    *state = KeyCaptureState::TypeMove;
+                    return;
+                }
                 Event::Key(Key::Char('f')) => {
                     if self.show_fen {
                         self.show_fen = false;
-                        write!(
-                            self.stdout,
-                            "{}{}{}",
-                            termion::cursor::Goto(1, 12),
-                            termion::clear::CurrentLine,
-                            style::Reset
-                        )
-                        .unwrap();
-                        self.reset_cursor();
+                        self.write_line(Line::Fen, String::new());
                     } else {
                         self.show_fen = true;
                         self.display_fen_string()
                     }
                 }
-                Event::Key(Key::Char('c')) => {
-                    if self.show_fen {
-                        self.copy_fen_to_clipboard();
-                    }
+                Event::Key(Key::Char('c')) if self.show_fen => {
+                    self.copy_fen_to_clipboard();
+                }
+                Event::Key(Key::Char('d')) => self.claim_draw(),
+                Event::Key(Key::Char('x')) => self.flip_board(),
+                Event::Key(Key::Char('u')) if self.network_color.is_none() => self.undo_move(),
+                Event::Key(Key::Char('y')) if self.network_color.is_none() => self.redo_move(),
+                Event::Key(Key::Char('o')) => self.offer_draw(),
+                Event::Key(Key::Char('a')) => self.answer_draw(true),
+                Event::Key(Key::Char('n')) => self.answer_draw(false),
+                Event::Key(Key::Char('R')) => self.resign(),
+                Event::Key(Key::Char('r')) if self.network_color.is_none() => {
+                    self.ply = self.record.moves().len();
+                    *state = KeyCaptureState::Replay;
+                    return;
                 }
+                Event::Key(Key::Char('s')) => self.save_pgn(),
+                Event::Key(Key::Char('p')) => self.copy_pgn_to_clipboard(),
                 Event::Key(Key::Char('q')) => {
                     *state = KeyCaptureState::ExitGame;
                     return;
@@ -1427,23 +1794,114 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
         }
     }
 
+    /// Reads moves typed in algebraic or UCI notation on the line under the board, until
+    /// escape is pressed.
+    fn handle_type_move_event(&mut self, state: &mut KeyCaptureState) {
+        self.show_hint("Enter:Play Tab:Complete ESC:Back to the board");
+        self.check_for_mate();
+        self.input = Some(String::new());
+        self.show_input();
+        loop {
+            let b = self.next_event();
+            let Some(input) = self.input.as_mut() else {
+                return;
+            };
+            match b {
+                Event::Key(Key::Esc) => break,
+                Event::Key(Key::Char('\n')) => self.play_typed_move(),
+                Event::Key(Key::Char('\t')) => self.complete_input(),
+                Event::Key(Key::Backspace) => {
+                    input.pop();
+                    self.show_input();
+                }
+                Event::Key(Key::Char(c)) if c.is_ascii_graphic() => {
+                    input.push(c);
+                    self.show_input();
+                }
+                _ => (),
+            }
+        }
+        self.input = None;
+        self.write_line(Line::Input, String::new());
+        self.display_message("");
+        *state = KeyCaptureState::Gameplay;
+    }
+
+    fn show_input(&mut self) {
+        let input = self.input.clone().unwrap_or_default();
+        self.write_line(Line::Input, format!("{}{}", INPUT_PROMPT, input));
+    }
+
+    /// Plays the move typed, or says why it cannot be played.
+    fn play_typed_move(&mut self) {
+        let Some(input) = self.input.clone() else {
+            return;
+        };
+        if input.is_empty() {
+            return;
+        }
+        if !self.can_move() {
+            self.display_message("It is not your move");
+            return;
+        }
+        match self.position.parse_move(&input) {
+            Ok(m) => {
+                self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
+                self.unhighlight_moves();
+                self.input = Some(String::new());
+                self.show_input();
+                self.display_message("");
+                self.play_own_move(&m);
+            }
+            Err(SanError::Ambiguous(san)) => {
+                let moves = self.position.san_matches(&san).unwrap_or_default();
+                let sans: Vec<String> = moves.iter().map(|m| self.position.san(m)).collect();
+                self.display_message(&format!("{} could be {}", san, sans.join(" or ")));
+            }
+            Err(e) => self.display_message(&format!("{}", e)),
+        }
+    }
+
+    /// Completes the move being typed as far as the legal moves starting with it agree,
+    /// listing them when there is more than one.
+    fn complete_input(&mut self) {
+        let Some(input) = self.input.clone() else {
+            return;
+        };
+        let completions = self.position.complete_move(&input);
+        let completed = common_prefix(&completions);
+        if completed.len() > input.len() {
+            self.input = Some(completed.to_string());
+            self.show_input();
+        }
+        match completions.len() {
+            0 => self.display_message(&format!("No legal move starts with {}", input)),
+            1 => self.display_message(""),
+            _ => self.display_message(&completions.join(" ")),
+        }
+    }
+
     fn handle_edit_board_event(&mut self, state: &mut KeyCaptureState, piece_to_place: &mut Piece) {
-        write!(
-            self.stdout,
-            "{}{}{}ESC:Exit c:Clear d:Delete{}k:King q:Queen r:Rook n:Knight b:Bishop p:Pawn{}",
-            termion::cursor::Goto(1, 10),
-            termion::clear::AfterCursor,
-            color::Bg(color::Red),
-            termion::cursor::Goto(1, 11),
-            style::Reset,
-        )
-        .unwrap();
+        self.show_hint("ESC:Exit c:Clear d:Delete");
+        self.write_line(
+            Line::Status,
+            format!(
+                "{}k:King q:Queen r:Rook n:Knight b:Bishop p:Pawn{}",
+                self.alert_color(),
+                style::Reset
+            ),
+        );
         self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
         self.unhighlight_moves();
-        self.reset_cursor();
+        self.stop_analysis();
+        self.stop_clock();
+        // The board may have no kings while it is edited
+        self.last_move = None;
+        self.checked_king = None;
+        self.redraw_board();
 
         loop {
-            let b = self.stdin.next().unwrap().unwrap();
+            let b = self.next_event();
             match b {
                 Event::Mouse(MouseEvent::Release(x, y)) => {
                     self.mouse_move_cursor(x, y);
@@ -1453,6 +1911,8 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
                 Event::Key(Key::Down) => self.down(),
                 Event::Key(Key::Up) => self.up(),
                 Event::Key(Key::Esc) => {
+                    // An edited board starts a new game from the edited position
+                    self.reset_record();
                     *state = KeyCaptureState::Gameplay;
                     break;
                 }
@@ -1462,7 +1922,6 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
                 }
                 Event::Key(Key::Char('d')) => {
                     self.empty_square(self.x, self.y);
-                    self.update_square(self.x, self.y);
                     self.reset_cursor();
                 }
                 Event::Key(Key::Char('k')) => {
@@ -1501,32 +1960,21 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
     }
 
     fn handle_colour_chooser_event(&mut self, state: &mut KeyCaptureState, piece_to_place: &Piece) {
-        write!(
-            self.stdout,
-            "{}{}{}w:White b:Black{}",
-            termion::cursor::Goto(1, 10),
-            termion::clear::AfterCursor,
-            color::Bg(color::Red),
-            style::Reset,
-        )
-        .unwrap();
-        self.reset_cursor();
+        self.show_hint("w:White b:Black");
         loop {
-            let b = self.stdin.next().unwrap().unwrap();
+            let b = self.next_event();
             match b {
                 Event::Key(Key::Char('w')) => {
-                    self.place_piece(piece_to_place.clone(), 0, self.x, self.y);
+                    self.place_piece(*piece_to_place, 0, self.x, self.y);
                     if *piece_to_place == Piece::King {
-                        self.king_coords[0] = [self.x, self.y];
-                        self.castling_rights[0] = [false, false];
+                        self.position.castling_rights[0] = [false, false];
                     }
                     break;
                 }
                 Event::Key(Key::Char('b')) => {
-                    self.place_piece(piece_to_place.clone(), 1, self.x, self.y);
+                    self.place_piece(*piece_to_place, 1, self.x, self.y);
                     if *piece_to_place == Piece::King {
-                        self.king_coords[1] = [self.x, self.y];
-                        self.castling_rights[1] = [false, false];
+                        self.position.castling_rights[1] = [false, false];
                     }
                     break;
                 }
@@ -1536,8 +1984,83 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
         *state = KeyCaptureState::EditBoard;
     }
 
-    fn run_game(&mut self) {
-        let mut state: KeyCaptureState = KeyCaptureState::Gameplay;
+    fn show_replay_move(&mut self, sans: &[String]) {
+        let text = if self.ply == 0 {
+            format!("Start 0/{}", sans.len())
+        } else {
+            let start = self.record.start();
+            let half_moves = start.turn + self.ply - 1;
+            let number = start.fullmoves + half_moves / 2;
+            let dots = if half_moves.is_multiple_of(2) {
+                "."
+            } else {
+                "..."
+            };
+            format!(
+                "Move {}/{}: {}{} {}",
+                self.ply,
+                sans.len(),
+                number,
+                dots,
+                sans[self.ply - 1]
+            )
+        };
+        self.write_line(Line::Status, text);
+    }
+
+    fn go_to_ply(&mut self, ply: usize, sans: &[String]) {
+        self.ply = ply.min(self.record.moves().len());
+        self.position = self.record.position_at(self.ply);
+        self.mark_last_move(self.ply);
+        self.redraw_board();
+        self.show_move_list(sans, self.ply);
+        self.show_replay_move(sans);
+        self.analyse(self.ply);
+    }
+
+    fn handle_replay_event(&mut self, state: &mut KeyCaptureState) {
+        self.show_hint("←/→:Step ↑/Home:Start ↓/End:End Enter:Play from here x:Flip q:Quit");
+        self.unhighlight_square(self.selected_piece[0], self.selected_piece[1]);
+        self.unhighlight_moves();
+
+        self.stop_clock();
+        let sans = self.record.sans();
+        self.go_to_ply(self.ply, &sans);
+        loop {
+            let b = self.next_event();
+            match b {
+                Event::Key(Key::Left) => self.go_to_ply(self.ply.saturating_sub(1), &sans),
+                Event::Key(Key::Right) => self.go_to_ply(self.ply + 1, &sans),
+                Event::Key(Key::Up) | Event::Key(Key::Home) => self.go_to_ply(0, &sans),
+                Event::Key(Key::Down) | Event::Key(Key::End) => self.go_to_ply(sans.len(), &sans),
+                Event::Key(Key::Char('x')) => self.flip_board(),
+                Event::Mouse(MouseEvent::Release(x, y)) => {
+                    if let Some(ply) = self.move_list_ply_at(x, y) {
+                        self.go_to_ply(ply, &sans);
+                    }
+                }
+                Event::Mouse(MouseEvent::Press(MouseButton::WheelUp, _, _)) => {
+                    self.scroll_move_list(true)
+                }
+                Event::Mouse(MouseEvent::Press(MouseButton::WheelDown, _, _)) => {
+                    self.scroll_move_list(false)
+                }
+                Event::Key(Key::Char('\n')) => {
+                    self.record.truncate(self.ply);
+                    self.reset_clock();
+                    *state = KeyCaptureState::Gameplay;
+                    return;
+                }
+                Event::Key(Key::Char('q')) => {
+                    *state = KeyCaptureState::ExitGame;
+                    return;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn run_game(&mut self, mut state: KeyCaptureState) {
         let mut piece_to_place: Piece = Piece::Empty;
         loop {
             match state {
@@ -1551,22 +2074,29 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
                 KeyCaptureState::PromotePawn => {
                     self.handle_promote_pawn_event(&mut state);
                 }
+                KeyCaptureState::ChooseDrop => self.handle_choose_drop_event(&mut state),
+                KeyCaptureState::TypeMove => self.handle_type_move_event(&mut state),
+                KeyCaptureState::Replay => self.handle_replay_event(&mut state),
                 _ => return,
             }
         }
     }
 
-    fn start(&mut self) {
-        self.init_board();
+    fn start(&mut self, replay: bool) {
+        if self.auto_flip {
+            self.flipped = self.position.turn == 1;
+        }
+        self.mark_last_move(self.record.moves().len());
+        self.fit_to_terminal();
         self.print_initial_board();
-        write!(self.stdout, "{}", termion::cursor::Goto(2, 1)).unwrap();
-        self.stdout.flush().unwrap();
-        if self.initial_fen.is_some() {
-            self.fill_board_from_fen_string(
-                self.initial_fen.clone().unwrap(),
-            );
+        self.reset_clock();
+        (self.cursor_x, self.cursor_y) = self.screen_coords(self.x, self.y);
+        self.reset_cursor();
+        if replay {
+            self.run_game(KeyCaptureState::Replay);
+        } else {
+            self.run_game(KeyCaptureState::Gameplay);
         }
-        self.run_game();
         write!(
             self.stdout,
             "{}{}{}",
@@ -1578,10 +2108,195 @@ impl<R: Iterator<Item = Result<Event, std::io::Error>>, W: Write> Game<R, W> {
     }
 }
 
+fn run_perft(depth: usize, fen: Option<Position>, split: bool) {
+    let position = fen.unwrap_or_default();
+
+    if split {
+        let mut total = 0;
+        for (m, nodes) in divide(&position, depth) {
+            println!("{}: {}", m, nodes);
+            total += nodes;
+        }
+        println!("\nNodes searched: {}", total);
+    } else {
+        println!("Nodes searched: {}", perft(&position, depth));
+    }
+}
+
+/// A Chess960 start position number picked from the clock, which is random enough to
+/// start a game with.
+fn random_chess960_number() -> usize {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    nanos as usize % CHESS960_POSITIONS
+}
+
 fn main() {
     let args = Cli::parse();
-    let stdout = MouseTerminal::from(stdout().lock().into_raw_mode().unwrap());
-    let stdin = stdin().lock();
-    init_game(stdout, stdin, args.fen); 
+    match args.command {
+        Some(Commands::Perft { depth, fen, divide }) => {
+            run_perft(depth, fen, divide);
+            return;
+        }
+        Some(Commands::Uci) => {
+            chess_term::uci::run(stdin().lock(), stdout());
+            return;
+        }
+        None => (),
+    }
+
+    let computer = args.vs_computer.map(|side| Computer {
+        color: match side {
+            Side::White => 1,
+            Side::Black => 0,
+        },
+        limit: match (args.depth, args.move_time) {
+            (Some(depth), _) => Some(Limit::Depth(depth)),
+            (None, Some(time)) => Some(Limit::Time(Duration::from_millis(time))),
+            (None, None) if args.time.is_some() => None,
+            (None, None) => Some(Limit::Time(Duration::from_secs(1))),
+        },
+    });
+    let time_control = args.time.map(|control| TimeControl {
+        method: match args.delay {
+            None => Increment::Fischer,
+            Some(Delay::Simple) => Increment::Delay,
+            Some(Delay::Bronstein) => Increment::Bronstein,
+        },
+        ..control
+    });
+
+    let engine = match args.engine {
+        Some(path) => match Engine::start(&path) {
+            Ok(engine) => Some(engine),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let variant = args.variant.unwrap_or_default();
+    let mut fen = match (&args.fen, args.chess960) {
+        (Some(fen), _) => match Position::from_variant_fen(fen, variant) {
+            Ok(position) => Some(position),
+            Err(e) => Cli::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!("invalid value '{}' for '--fen <FEN>': {}", fen, e),
+                )
+                .exit(),
+        },
+        (None, Some(_)) if !variant.has_castling() => Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("{} cannot be played as Chess960", variant),
+            )
+            .exit(),
+        (None, Some(number)) => {
+            let number = number.map_or_else(random_chess960_number, usize::from);
+            let mut position = Position::chess960_start(number);
+            position.variant = variant;
+            Some(position)
+        }
+        (None, None) if variant != Variant::Standard => Some(variant.start()),
+        (None, None) => None,
+    };
+    let network = match (args.host, &args.connect) {
+        (Some(port), _) => {
+            let start = fen.clone().unwrap_or_default();
+            Host::listen(port)
+                .and_then(|host| {
+                    let port = host.local_addr()?.port();
+                    println!("Waiting for a player to connect on port {}...", port);
+                    host.accept(&start, 1)
+                })
+                .map(Some)
+        }
+        (None, Some(addr)) => Connection::connect(addr).map(|(connection, start)| {
+            fen = Some(start);
+            Some(connection)
+        }),
+        (None, None) => Ok(None),
+    };
+    let network = match network {
+        Ok(network) => network,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let config = match args.config.clone().or_else(Config::default_path) {
+        Some(path) => Config::load(&path, args.config.is_some()),
+        None => Ok(Config::default()),
+    };
+    let theme = match config.and_then(|config| config.theme(args.theme.as_deref())) {
+        Ok(theme) => theme,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            if let ConfigError::UnknownTheme(_) = e {
+                eprintln!("built-in themes: {}", BUILTIN_THEMES.join(", "));
+            }
+            std::process::exit(1);
+        }
+    };
 
+    let (record, replay) = match args.pgn {
+        Some(path) => match load_pgn(&path, args.game) {
+            Ok(record) => (record, true),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            let position = fen.unwrap_or_default();
+            let record = new_record(&position, computer, engine.as_ref(), time_control);
+            (record, false)
+        }
+    };
+
+    let settings = Settings {
+        pgn_path: args.save_pgn,
+        replay,
+        computer,
+        engine,
+        time_control,
+        // Whoever plays Black sees the board from their side
+        flipped: args.flip
+            || matches!(args.vs_computer, Some(Side::Black))
+            || network.as_ref().is_some_and(|network| network.color == 1),
+        auto_flip: args.auto_flip,
+        network,
+        theme,
+        palette: match args.colors {
+            None => Palette::detect(),
+            Some(Colors::Truecolor) => Palette::TrueColor,
+            Some(Colors::Ansi256) => Palette::Ansi256,
+            Some(Colors::Ansi16) => Palette::Ansi16,
+            Some(Colors::None) => Palette::NoColor,
+        },
+        pieces: match args.pieces {
+            None => PieceSet::detect(),
+            Some(Pieces::Unicode) => PieceSet::Unicode,
+            Some(Pieces::Ascii) => PieceSet::Ascii,
+        },
+        layout: args.square_size,
+    };
+    let stdout = MouseTerminal::from(stdout().lock().into_raw_mode().unwrap());
+    // Input is read on its own thread so the engine, clocks, other player and size of the
+    // terminal can be followed while waiting for keys
+    let (sender, events) = mpsc::channel();
+    thread::spawn(move || {
+        for event in stdin().events() {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    let stdin = iter::from_fn(move || events.try_recv().ok());
+    init_game(stdout, stdin, record, settings);
 }
@@ -0,0 +1,312 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::chess_move::ChessMove;
+use crate::position::Position;
+use crate::variant::Variant;
+
+/// Version of the line protocol, sent in the `hello` line by both sides.
+const PROTOCOL_VERSION: u32 = 3;
+/// How long the other side gets to answer while setting up the game.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a network game could not go on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetError {
+    /// The connection could not be made, or failed.
+    Io(String),
+    /// The other side sent something it should not have.
+    Protocol(String),
+    /// The other side closed the connection.
+    Disconnected,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "{}", e),
+            NetError::Protocol(e) => write!(f, "{}", e),
+            NetError::Disconnected => write!(f, "the other side left the game"),
+        }
+    }
+}
+
+impl Error for NetError {}
+
+/// A line of the protocol. Moves are in UCI notation, the game is given as a FEN.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Hello(u32),
+    /// Sent by the host: the colour the guest plays, the variant and the starting position.
+    Game {
+        guest: usize,
+        variant: Variant,
+        fen: String,
+    },
+    Move(String),
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    /// A draw by repetition or the fifty-move rule, claimed by the side to move.
+    ClaimDraw,
+    Resign,
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello(version) => write!(f, "hello chess-term {}", version),
+            Message::Game {
+                guest,
+                variant,
+                fen,
+            } => {
+                let colour = if *guest == 0 { "white" } else { "black" };
+                write!(f, "game {} {} {}", colour, variant.key(), fen)
+            }
+            Message::Move(uci) => write!(f, "move {}", uci),
+            Message::OfferDraw => write!(f, "draw offer"),
+            Message::AcceptDraw => write!(f, "draw accept"),
+            Message::DeclineDraw => write!(f, "draw decline"),
+            Message::ClaimDraw => write!(f, "draw claim"),
+            Message::Resign => write!(f, "resign"),
+        }
+    }
+}
+
+/// Parses a line of the protocol.
+pub fn parse_message(line: &str) -> Option<Message> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["hello", "chess-term", version] => version.parse().ok().map(Message::Hello),
+        ["game", colour, variant, fen @ ..] if !fen.is_empty() => {
+            let guest = match *colour {
+                "white" => 0,
+                "black" => 1,
+                _ => return None,
+            };
+            Some(Message::Game {
+                guest,
+                variant: variant.parse().ok()?,
+                fen: fen.join(" "),
+            })
+        }
+        ["move", uci] => Some(Message::Move(uci.to_string())),
+        ["draw", "offer"] => Some(Message::OfferDraw),
+        ["draw", "accept"] => Some(Message::AcceptDraw),
+        ["draw", "decline"] => Some(Message::DeclineDraw),
+        ["draw", "claim"] => Some(Message::ClaimDraw),
+        ["resign"] => Some(Message::Resign),
+        _ => None,
+    }
+}
+
+/// Something the other player did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetEvent {
+    /// A move, already checked to be legal.
+    Move(ChessMove),
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    /// A draw claim, made on the other player's turn. Whether the game history allows it is
+    /// left to the receiver.
+    ClaimDraw,
+    Resign,
+}
+
+/// Waits for a player to connect, to host a game.
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    /// Listens on `port` on every interface. Port 0 picks a free one.
+    pub fn listen(port: u16) -> Result<Self, NetError> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .map_err(|e| NetError::Io(format!("port {}: {}", port, e)))?;
+        Ok(Self { listener })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, NetError> {
+        self.listener
+            .local_addr()
+            .map_err(|e| NetError::Io(e.to_string()))
+    }
+
+    /// Waits for a player and starts a game from `start` with them playing `guest`.
+    pub fn accept(&self, start: &Position, guest: usize) -> Result<Connection, NetError> {
+        let (stream, _) = self
+            .listener
+            .accept()
+            .map_err(|e| NetError::Io(e.to_string()))?;
+        let mut connection = Connection::new(stream, 1 - guest)?;
+        connection.hello()?;
+        // Shredder-FEN castling rights keep a Chess960 game from being read as a standard one
+        let fen = if start.chess960 {
+            start.to_shredder_fen()
+        } else {
+            start.to_fen()
+        };
+        connection.send(&Message::Game {
+            guest,
+            variant: start.variant,
+            fen,
+        })?;
+        Ok(connection)
+    }
+}
+
+/// A game against a player on another terminal.
+pub struct Connection {
+    /// The colour played on this end.
+    pub color: usize,
+    stream: TcpStream,
+    lines: Receiver<String>,
+}
+
+impl Connection {
+    /// Joins the game hosted at `addr`, returning it with the position to start from.
+    pub fn connect(addr: &str) -> Result<(Self, Position), NetError> {
+        let stream =
+            TcpStream::connect(addr).map_err(|e| NetError::Io(format!("{}: {}", addr, e)))?;
+        let mut connection = Connection::new(stream, 0)?;
+        connection.hello()?;
+        match parse_message(&connection.read_line()?) {
+            Some(Message::Game {
+                guest,
+                variant,
+                fen,
+            }) => {
+                let start = Position::from_variant_fen(&fen, variant).map_err(|e| {
+                    NetError::Protocol(format!("the host sent a bad position: {}", e))
+                })?;
+                connection.color = guest;
+                Ok((connection, start))
+            }
+            _ => Err(NetError::Protocol(
+                "the host did not start a game".to_string(),
+            )),
+        }
+    }
+
+    fn new(stream: TcpStream, color: usize) -> Result<Self, NetError> {
+        let reader = stream
+            .try_clone()
+            .map_err(|e| NetError::Io(e.to_string()))?;
+
+        // Reading happens on its own thread so the connection can be polled without blocking
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            color,
+            stream,
+            lines,
+        })
+    }
+
+    /// Greets the other side and checks it speaks the same protocol.
+    fn hello(&mut self) -> Result<(), NetError> {
+        self.send(&Message::Hello(PROTOCOL_VERSION))?;
+        match parse_message(&self.read_line()?) {
+            Some(Message::Hello(PROTOCOL_VERSION)) => Ok(()),
+            Some(Message::Hello(version)) => Err(NetError::Protocol(format!(
+                "the other side speaks version {} of the protocol, not {}",
+                version, PROTOCOL_VERSION
+            ))),
+            _ => Err(NetError::Protocol(
+                "the other side is not chess-term".to_string(),
+            )),
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String, NetError> {
+        match self.lines.recv_timeout(HANDSHAKE_TIMEOUT) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(NetError::Protocol(
+                "the other side did not answer".to_string(),
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(NetError::Disconnected),
+        }
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        writeln!(self.stream, "{}", message)
+            .and_then(|_| self.stream.flush())
+            .map_err(|e| NetError::Io(e.to_string()))
+    }
+
+    /// Sends a move played on this end from `position`, which must be legal there.
+    pub fn send_move(&mut self, position: &Position, m: &ChessMove) -> Result<(), NetError> {
+        let uci = m.to_uci();
+        if position.turn != self.color || position.parse_uci_move(&uci) != Some(*m) {
+            return Err(NetError::Protocol(format!(
+                "{} is not a legal move to send",
+                uci
+            )));
+        }
+        self.send(&Message::Move(uci))
+    }
+
+    /// The next thing the other player did, without waiting for one. Moves are checked
+    /// against the legal moves in `position`, the position the game has reached.
+    pub fn poll(&mut self, position: &Position) -> Result<Option<NetEvent>, NetError> {
+        let line = match self.lines.try_recv() {
+            Ok(line) => line,
+            Err(TryRecvError::Empty) => return Ok(None),
+            Err(TryRecvError::Disconnected) => return Err(NetError::Disconnected),
+        };
+        let event = match parse_message(&line) {
+            Some(Message::Move(uci)) => {
+                if position.turn == self.color {
+                    return Err(NetError::Protocol(format!(
+                        "the other side played {} out of turn",
+                        uci
+                    )));
+                }
+                let m = position.parse_uci_move(&uci).ok_or_else(|| {
+                    NetError::Protocol(format!("the other side played an illegal move {}", uci))
+                })?;
+                NetEvent::Move(m)
+            }
+            Some(Message::OfferDraw) => NetEvent::OfferDraw,
+            Some(Message::AcceptDraw) => NetEvent::AcceptDraw,
+            Some(Message::DeclineDraw) => NetEvent::DeclineDraw,
+            Some(Message::ClaimDraw) => {
+                if position.turn == self.color {
+                    return Err(NetError::Protocol(
+                        "the other side claimed a draw out of turn".to_string(),
+                    ));
+                }
+                NetEvent::ClaimDraw
+            }
+            Some(Message::Resign) => NetEvent::Resign,
+            _ => {
+                return Err(NetError::Protocol(format!(
+                    "the other side sent '{}'",
+                    line.trim()
+                )))
+            }
+        };
+        Ok(Some(event))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // Closes the socket for the reading thread too, so the other side sees it go
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
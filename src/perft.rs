@@ -0,0 +1,36 @@
+use rayon::prelude::*;
+
+use crate::chess_move::ChessMove;
+use crate::position::Position;
+
+/// Counts the leaf nodes of the legal move tree `depth` plies deep.
+pub fn perft(position: &Position, depth: usize) -> u64 {
+    let moves = position.legal_moves();
+    if depth <= 1 {
+        return if depth == 0 { 1 } else { moves.len() as u64 };
+    }
+
+    moves
+        .iter()
+        .map(|m| {
+            let mut next = position.clone();
+            next.make_move(m);
+            perft(&next, depth - 1)
+        })
+        .sum()
+}
+
+/// Perft split by root move, searched in parallel. Moves are sorted by their UCI string.
+pub fn divide(position: &Position, depth: usize) -> Vec<(ChessMove, u64)> {
+    let mut moves = position.legal_moves();
+    moves.sort_by_key(|m| m.to_uci());
+
+    moves
+        .into_par_iter()
+        .map(|m| {
+            let mut next = position.clone();
+            next.make_move(&m);
+            (m, perft(&next, depth.saturating_sub(1)))
+        })
+        .collect()
+}
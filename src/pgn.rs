@@ -0,0 +1,374 @@
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::fen::FenError;
+use crate::position::{GameStatus, Position};
+use crate::record::{GameRecord, GameResult};
+use crate::san::SanError;
+use crate::variant::Variant;
+
+/// Why a PGN file could not be read. Game and ply numbers start at 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnError {
+    BadTag(String),
+    UnterminatedComment,
+    BadFen(usize, FenError),
+    /// A `Variant` tag naming rules that are not known.
+    UnknownVariant(usize, String),
+    BadMove {
+        game: usize,
+        ply: usize,
+        error: SanError,
+    },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::BadTag(tag) => write!(f, "malformed tag pair [{}]", tag),
+            PgnError::UnterminatedComment => write!(f, "comment or variation is never closed"),
+            PgnError::BadFen(game, e) => write!(f, "game {}: bad FEN tag: {}", game, e),
+            PgnError::UnknownVariant(game, variant) => {
+                write!(f, "game {}: unknown variant {}", game, variant)
+            }
+            PgnError::BadMove { game, ply, error } => {
+                write!(f, "game {}, ply {}: {}", game, ply, error)
+            }
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+enum Token {
+    Tag(String, String),
+    Word(String),
+    /// The time left from a `[%clk]` command in a comment.
+    Clock(Duration),
+}
+
+const LINE_LENGTH: usize = 79;
+
+impl GameRecord {
+    /// Exports the game in PGN export format, adding a `Variant` tag for anything but
+    /// standard chess and `SetUp`/`FEN` tags when it did not start from the variant's
+    /// starting position.
+    pub fn to_pgn(&self) -> String {
+        let result = self.result().to_string();
+        let mut tags = self.tags.clone();
+        for (name, value) in tags.iter_mut() {
+            if name == "Result" {
+                *value = result.clone();
+            }
+        }
+        let start = self.start();
+        let variant = match (start.variant, start.chess960) {
+            (Variant::Standard, false) => None,
+            (Variant::Standard, true) => Some("Chess960".to_string()),
+            (variant, _) => Some(variant.to_string()),
+        };
+        if let Some(variant) = variant {
+            tags.retain(|(name, _)| name != "Variant");
+            tags.push(("Variant".to_string(), variant));
+        }
+        if *start != start.variant.start() {
+            tags.retain(|(name, _)| name != "SetUp" && name != "FEN");
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start.to_fen()));
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn += &format!("[{} \"{}\"]\n", name, value);
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        let mut turn = self.start().turn;
+        let mut fullmoves = self.start().fullmoves;
+        for (i, (san, clock)) in self.sans().into_iter().zip(self.clocks()).enumerate() {
+            if turn == 0 {
+                tokens.push(format!("{}.", fullmoves));
+            } else if i == 0 || self.clocks()[i - 1].is_some() {
+                // A comment in between means the move number has to be repeated
+                tokens.push(format!("{}...", fullmoves));
+            }
+            tokens.push(san);
+            if let Some(clock) = clock {
+                tokens.push(format!("{{[%clk {}]}}", format_clk(*clock)));
+            }
+
+            if turn == 1 {
+                fullmoves += 1;
+            }
+            turn = 1 - turn;
+        }
+        tokens.push(result);
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+                pgn += &line;
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &token;
+        }
+        pgn += &line;
+        pgn.push('\n');
+        pgn
+    }
+}
+
+/// Formats time left as `H:MM:SS` for a `[%clk]` command.
+fn format_clk(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Reads the time from the first `[%clk H:MM:SS]` command in a comment, allowing fractions
+/// of a second.
+fn parse_clk(comment: &str) -> Option<Duration> {
+    let (_, rest) = comment.split_once("[%clk")?;
+    let (time, _) = rest.split_once(']')?;
+    let mut seconds = 0.0;
+    for part in time.trim().split(':') {
+        let value: f64 = part.parse().ok()?;
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Today's date in the `YYYY.MM.DD` form used by the PGN `Date` tag.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or(0) as i64;
+
+    // Convert days since 1970-01-01 to a civil date
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Splits PGN text into tag pairs, movetext words and clock times, dropping the rest of
+/// comments, variations, NAGs, escaped lines and move numbers.
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+
+    let flush = |word: &mut String, tokens: &mut Vec<Token>| {
+        if !word.is_empty() && !word.chars().all(|c| c.is_ascii_digit()) {
+            tokens.push(Token::Word(word.clone()));
+        }
+        word.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        let at_line_start = line_start;
+        line_start = c == '\n';
+        match c {
+            '%' if at_line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                flush(&mut word, &mut tokens);
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                }
+            }
+            '{' => {
+                flush(&mut word, &mut tokens);
+                let mut comment = String::new();
+                loop {
+                    match chars.next().ok_or(PgnError::UnterminatedComment)? {
+                        '}' => break,
+                        c => comment.push(c),
+                    }
+                }
+                if let Some(clock) = parse_clk(&comment) {
+                    tokens.push(Token::Clock(clock));
+                }
+            }
+            '(' => {
+                flush(&mut word, &mut tokens);
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next().ok_or(PgnError::UnterminatedComment)? {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        '{' => {
+                            chars
+                                .by_ref()
+                                .find(|&c| c == '}')
+                                .ok_or(PgnError::UnterminatedComment)?;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            '[' => {
+                flush(&mut word, &mut tokens);
+                let mut tag = String::new();
+                let mut in_string = false;
+                loop {
+                    let c = chars.next().ok_or(PgnError::BadTag(tag.clone()))?;
+                    match c {
+                        '\\' if in_string => {
+                            if let Some(escaped) = chars.next() {
+                                tag.push(escaped);
+                            }
+                        }
+                        '"' => in_string = !in_string,
+                        ']' if !in_string => break,
+                        _ => tag.push(c),
+                    }
+                }
+                let (name, value) = tag
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .ok_or(PgnError::BadTag(tag.clone()))?;
+                tokens.push(Token::Tag(name.to_string(), value.trim().to_string()));
+            }
+            '$' => {
+                flush(&mut word, &mut tokens);
+                while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                }
+            }
+            '.' => flush(&mut word, &mut tokens),
+            c if c.is_whitespace() => flush(&mut word, &mut tokens),
+            c => word.push(c),
+        }
+    }
+    flush(&mut word, &mut tokens);
+
+    Ok(tokens)
+}
+
+/// Reads every game in a PGN file, resolving the moves against the legal moves of each
+/// position.
+pub fn parse_games(text: &str) -> Result<Vec<GameRecord>, PgnError> {
+    let mut games: Vec<GameRecord> = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut words: Vec<Token> = Vec::new();
+
+    let mut tokens = tokenize(text)?.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let finished = match token {
+            Token::Tag(name, value) => {
+                tags.push((name, value));
+                false
+            }
+            Token::Word(word) => {
+                let is_result = GameResult::from_token(&word).is_some();
+                words.push(Token::Word(word));
+                is_result
+            }
+            clock => {
+                words.push(clock);
+                false
+            }
+        };
+
+        let next_is_tag = matches!(tokens.peek(), Some(Token::Tag(..)));
+        if finished || tokens.peek().is_none() || (next_is_tag && !words.is_empty()) {
+            let record = build_game(games.len() + 1, &tags, &words)?;
+            games.push(record);
+            tags.clear();
+            words.clear();
+        }
+    }
+
+    Ok(games)
+}
+
+fn build_game(
+    game: usize,
+    tags: &[(String, String)],
+    words: &[Token],
+) -> Result<GameRecord, PgnError> {
+    let (variant, chess960) = match tags.iter().find(|(name, _)| name == "Variant") {
+        Some((_, name)) => match name.to_ascii_lowercase().as_str() {
+            "chess960" | "fischerandom" => (Variant::Standard, true),
+            _ => match name.parse::<Variant>() {
+                Ok(variant) => (variant, false),
+                Err(_) => return Err(PgnError::UnknownVariant(game, name.clone())),
+            },
+        },
+        None => (Variant::Standard, false),
+    };
+    let mut start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => {
+            Position::from_variant_fen(fen, variant).map_err(|e| PgnError::BadFen(game, e))?
+        }
+        None => variant.start(),
+    };
+    start.chess960 |= chess960;
+
+    let mut record = GameRecord::new(start);
+    for (name, value) in tags {
+        record.set_tag(name, value);
+    }
+
+    for token in words {
+        let word = match token {
+            Token::Word(word) => word,
+            Token::Clock(clock) => {
+                record.set_clock(*clock);
+                continue;
+            }
+            Token::Tag(..) => continue,
+        };
+        let position = record.position();
+        if let Some(result) = GameResult::from_token(word) {
+            if position.status() == GameStatus::Ongoing && result != GameResult::Ongoing {
+                record.outcome = Some(result);
+            }
+            break;
+        }
+
+        let m = position
+            .parse_san(word)
+            .map_err(|error| PgnError::BadMove {
+                game,
+                ply: record.moves().len() + 1,
+                error,
+            })?;
+        record.push(m);
+    }
+
+    Ok(record)
+}
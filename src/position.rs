@@ -0,0 +1,998 @@
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::chess_move::{parse_square, piece_from_letter, ChessMove};
+use crate::variant::Variant;
+
+/// The kind of piece standing on a square.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Piece {
+    King,
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+    Pawn,
+    Empty,
+}
+
+/// A single step a piece can take, as seen from White's side of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+pub enum Move {
+    U,
+    D,
+    R,
+    L,
+    UL,
+    DL,
+    UR,
+    DR,
+    RRU,
+    RUU,
+    RRD,
+    RDD,
+    LUU,
+    LLU,
+    LLD,
+    LDD,
+}
+
+/// One square of the board. `color` is 0 for White, 1 for Black and 2 for an empty square.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Square {
+    pub piece: Piece,
+    pub color: usize,
+}
+
+/// Whether the side to move can still play on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate,
+    Stalemate,
+    /// 75 moves by each side without a capture or pawn move.
+    SeventyFiveMoveRule,
+    /// The same position has occurred five times.
+    FivefoldRepetition,
+    /// Neither side has enough pieces left to checkmate.
+    InsufficientMaterial,
+    /// A side (0 White, 1 Black) has won by the rules of the variant being played.
+    VariantWin(usize),
+    /// A draw by the rules of the variant being played.
+    VariantDraw,
+}
+
+/// The parts of a position that decide whether it is a repetition of another.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RepetitionKey {
+    board: [[Square; 8]; 8],
+    turn: usize,
+    castling_rights: [[bool; 2]; 2],
+    en_passant: Option<[usize; 2]>,
+    checks: [usize; 2],
+    pockets: [[usize; POCKET_PIECES.len()]; 2],
+}
+
+/// A chess position: piece placement plus everything FEN records about the game state.
+///
+/// Squares are addressed as `[x, y]` with `[0, 0]` being a8 and `[7, 7]` being h1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub board: [[Square; 8]; 8],
+    pub turn: usize,
+    /// `[colour][0]` is kingside, `[colour][1]` is queenside.
+    pub castling_rights: [[bool; 2]; 2],
+    /// The file of the rook each castling right belongs to, indexed like `castling_rights`.
+    /// Always the corners outside Chess960.
+    pub castling_rooks: [[usize; 2]; 2],
+    /// Whether castling follows Chess960 rules, where a castling move is given as the king
+    /// taking its own rook.
+    pub chess960: bool,
+    pub variant: Variant,
+    /// How many times each side has given check, counted in Three-check.
+    pub checks: [usize; 2],
+    /// How many of each of `POCKET_PIECES` each side holds in Crazyhouse.
+    pub pockets: [[usize; POCKET_PIECES.len()]; 2],
+    /// The squares of pieces that were pawns before promoting, which go back to being pawns
+    /// when captured in Crazyhouse.
+    pub promoted: [[bool; 8]; 8],
+    /// The square a pawn may move to when capturing en passant.
+    pub en_passant: Option<[usize; 2]>,
+    pub king_coords: [[usize; 2]; 2],
+    pub halfmove_clock: usize,
+    pub fullmoves: usize,
+}
+
+const KING_MOVES: [Move; 8] = [
+    Move::U,
+    Move::D,
+    Move::L,
+    Move::R,
+    Move::UR,
+    Move::UL,
+    Move::DR,
+    Move::DL,
+];
+const ROOK_MOVES: [Move; 4] = [Move::U, Move::D, Move::L, Move::R];
+const BISHOP_MOVES: [Move; 4] = [Move::UR, Move::UL, Move::DR, Move::DL];
+const KNIGHT_MOVES: [Move; 8] = [
+    Move::RRU,
+    Move::RUU,
+    Move::RRD,
+    Move::RDD,
+    Move::LLU,
+    Move::LUU,
+    Move::LLD,
+    Move::LDD,
+];
+/// The files the king and rook end up on when castling, kingside then queenside.
+const CASTLED_FILES: [(usize, usize); 2] = [(6, 5), (2, 3)];
+/// The number of Chess960 start positions.
+pub const CHESS960_POSITIONS: usize = 960;
+/// The pieces a Crazyhouse pocket can hold, in the order they are shown.
+pub const POCKET_PIECES: [Piece; 5] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+];
+const WHITE_PAWN_MOVES: [Move; 3] = [Move::U, Move::UL, Move::UR];
+const BLACK_PAWN_MOVES: [Move; 3] = [Move::D, Move::DL, Move::DR];
+
+pub fn get_change_from_move(m: &Move) -> [isize; 2] {
+    match m {
+        Move::U => [0, -1],
+        Move::D => [0, 1],
+        Move::R => [1, 0],
+        Move::L => [-1, 0],
+        Move::UR => [1, -1],
+        Move::UL => [-1, -1],
+        Move::DR => [1, 1],
+        Move::DL => [-1, 1],
+        Move::RRU => [2, -1],
+        Move::RUU => [1, -2],
+        Move::RRD => [2, 1],
+        Move::RDD => [1, 2],
+        Move::LLU => [-2, -1],
+        Move::LUU => [-1, -2],
+        Move::LLD => [-2, 1],
+        Move::LDD => [-1, 2],
+    }
+}
+
+impl Square {
+    pub fn new(piece: Piece, color: usize) -> Self {
+        Self { piece, color }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(Piece::Empty, 2)
+    }
+
+    /// The directions this piece moves in.
+    pub fn moves(&self) -> &'static [Move] {
+        match self.piece {
+            Piece::King | Piece::Queen => &KING_MOVES,
+            Piece::Rook => &ROOK_MOVES,
+            Piece::Bishop => &BISHOP_MOVES,
+            Piece::Knight => &KNIGHT_MOVES,
+            Piece::Pawn => {
+                if self.color == 0 {
+                    &WHITE_PAWN_MOVES
+                } else {
+                    &BLACK_PAWN_MOVES
+                }
+            }
+            Piece::Empty => &[],
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Position {
+    /// The standard starting position.
+    pub fn new() -> Self {
+        let back_rank = [
+            Piece::Rook,
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Queen,
+            Piece::King,
+            Piece::Bishop,
+            Piece::Knight,
+            Piece::Rook,
+        ];
+        let mut board = [[Square::empty(); 8]; 8];
+        for x in 0..8 {
+            board[0][x] = Square::new(back_rank[x], 1);
+            board[1][x] = Square::new(Piece::Pawn, 1);
+            board[6][x] = Square::new(Piece::Pawn, 0);
+            board[7][x] = Square::new(back_rank[x], 0);
+        }
+
+        Self {
+            board,
+            turn: 0,
+            castling_rights: [[true, true], [true, true]],
+            castling_rooks: [[7, 0], [7, 0]],
+            chess960: false,
+            variant: Variant::Standard,
+            checks: [0, 0],
+            pockets: [[0; POCKET_PIECES.len()]; 2],
+            promoted: [[false; 8]; 8],
+            en_passant: None,
+            king_coords: [[4, 7], [4, 0]],
+            halfmove_clock: 0,
+            fullmoves: 1,
+        }
+    }
+
+    /// Chess960 start position `number`, from 0 to 959, in the standard numbering where
+    /// 518 is the usual starting position.
+    pub fn chess960_start(number: usize) -> Self {
+        assert!(
+            number < CHESS960_POSITIONS,
+            "no Chess960 position {}",
+            number
+        );
+        let mut back_rank = [Piece::Empty; 8];
+        let mut n = number;
+        // Bishops on opposite colours, then the queen and knights on the squares left over
+        back_rank[n % 4 * 2 + 1] = Piece::Bishop;
+        n /= 4;
+        back_rank[n % 4 * 2] = Piece::Bishop;
+        n /= 4;
+        let mut place_on_empty = |piece: Piece, index: usize| {
+            let x = (0..8)
+                .filter(|&x| back_rank[x] == Piece::Empty)
+                .nth(index)
+                .unwrap();
+            back_rank[x] = piece;
+        };
+        place_on_empty(Piece::Queen, n % 6);
+        n /= 6;
+        let knights = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+        let (first, second) = knights[n];
+        // The second knight is counted before the first takes its square
+        place_on_empty(Piece::Knight, second);
+        place_on_empty(Piece::Knight, first);
+        // The king always stands between the rooks
+        for piece in [Piece::Rook, Piece::King, Piece::Rook] {
+            place_on_empty(piece, 0);
+        }
+
+        let mut position = Self::empty();
+        for (x, &piece) in back_rank.iter().enumerate() {
+            position.place_piece(piece, 1, x, 0);
+            position.place_piece(Piece::Pawn, 1, x, 1);
+            position.place_piece(Piece::Pawn, 0, x, 6);
+            position.place_piece(piece, 0, x, 7);
+        }
+        let rooks: Vec<usize> = (0..8).filter(|&x| back_rank[x] == Piece::Rook).collect();
+        position.castling_rights = [[true, true], [true, true]];
+        position.castling_rooks = [[rooks[1], rooks[0]]; 2];
+        position.chess960 = true;
+        position
+    }
+
+    /// A position with no pieces on the board and White to move.
+    pub fn empty() -> Self {
+        Self {
+            board: [[Square::empty(); 8]; 8],
+            turn: 0,
+            castling_rights: [[false, false], [false, false]],
+            castling_rooks: [[7, 0], [7, 0]],
+            chess960: false,
+            variant: Variant::Standard,
+            checks: [0, 0],
+            pockets: [[0; POCKET_PIECES.len()]; 2],
+            promoted: [[false; 8]; 8],
+            en_passant: None,
+            king_coords: [[4, 7], [4, 0]],
+            halfmove_clock: 0,
+            fullmoves: 1,
+        }
+    }
+
+    pub fn square(&self, x: usize, y: usize) -> Square {
+        self.board[y][x]
+    }
+
+    pub fn place_piece(&mut self, p: Piece, color: usize, x: usize, y: usize) {
+        self.board[y][x] = Square::new(p, color);
+        self.promoted[y][x] = false;
+        if p == Piece::King {
+            self.king_coords[color] = [x, y];
+        }
+    }
+
+    pub fn empty_square(&mut self, x: usize, y: usize) {
+        self.board[y][x] = Square::empty();
+        self.promoted[y][x] = false;
+    }
+
+    /// How many of `piece` `color` holds in its pocket.
+    pub fn pocket(&self, color: usize, piece: Piece) -> usize {
+        pocket_index(piece).map_or(0, |i| self.pockets[color][i])
+    }
+
+    // Valid move finder helper functions
+    /// Whether any piece of `color` attacks the given square.
+    pub fn is_attacked_by(&self, x: isize, y: isize, color: usize) -> bool {
+        for dir in Move::iter() {
+            let change = get_change_from_move(&dir);
+            let range = if KNIGHT_MOVES.contains(&dir) { 1 } else { 7 };
+            let mut tmp_x: isize = x;
+            let mut tmp_y: isize = y;
+
+            for i in 0..range {
+                tmp_x += change[0];
+                tmp_y += change[1];
+                if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
+                    break;
+                }
+
+                let square = self.board[tmp_y as usize][tmp_x as usize];
+                if square.piece == Piece::Empty {
+                    continue;
+                }
+
+                if square.color != color {
+                    break;
+                }
+
+                let attacks = match square.piece {
+                    Piece::King => i == 0 && KING_MOVES.contains(&dir),
+                    Piece::Pawn => {
+                        // Pawns attack towards the opposite side, so look behind them
+                        let behind = if color == 0 {
+                            [Move::DL, Move::DR]
+                        } else {
+                            [Move::UL, Move::UR]
+                        };
+                        i == 0 && behind.contains(&dir)
+                    }
+                    _ => square.moves().contains(&dir),
+                };
+                if attacks {
+                    return true;
+                }
+                break;
+            }
+        }
+
+        false
+    }
+
+    /// Whether the side not to move attacks the given square.
+    pub fn is_attacked(&self, x: isize, y: isize) -> bool {
+        self.is_attacked_by(x, y, 1 - self.turn)
+    }
+
+    /// Whether the king of the side to move is in check.
+    pub fn in_check(&self) -> bool {
+        self.king_attacked(self.turn)
+    }
+
+    /// Whether the king of `color` is in check. Never so in variants without royal kings,
+    /// nor once the king has been blown up.
+    pub fn king_attacked(&self, color: usize) -> bool {
+        if !self.variant.has_royal_king() || !self.has_king(color) {
+            return false;
+        }
+        let [x, y] = self.king_coords[color];
+        self.king_square_attacked(x, y, color)
+    }
+
+    /// Whether the king of `color` would be attacked on `[x, y]`. In Atomic a king next to
+    /// the enemy king is safe, as taking it would blow up the taker's own king.
+    fn king_square_attacked(&self, x: usize, y: usize, color: usize) -> bool {
+        if self.variant.has_explosions() && self.has_king(1 - color) {
+            let [enemy_x, enemy_y] = self.king_coords[1 - color];
+            if x.abs_diff(enemy_x) <= 1 && y.abs_diff(enemy_y) <= 1 {
+                return false;
+            }
+        }
+        self.is_attacked_by(x as isize, y as isize, 1 - color)
+    }
+
+    /// Whether `color` still has its king, which a variant may take off the board.
+    pub fn has_king(&self, color: usize) -> bool {
+        let [x, y] = self.king_coords[color];
+        self.board[y][x] == Square::new(Piece::King, color)
+    }
+
+    fn find_pawn_destinations(&self, x: usize, y: usize) -> Vec<[usize; 2]> {
+        let square = self.board[y][x];
+        let mut moves: Vec<[usize; 2]> = Vec::new();
+        let start_rank = if square.color == 0 { 6 } else { 1 };
+
+        for m in square.moves() {
+            let change = get_change_from_move(m);
+            let tmp_x: isize = x as isize + change[0];
+            let tmp_y: isize = y as isize + change[1];
+            if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
+                continue;
+            }
+
+            let target = [tmp_x as usize, tmp_y as usize];
+            let target_square = self.board[target[1]][target[0]];
+            match m {
+                Move::U | Move::D => {
+                    if target_square.piece != Piece::Empty {
+                        continue;
+                    }
+                    moves.push(target);
+
+                    let double_y = (tmp_y + change[1]) as usize;
+                    if y == start_rank && self.board[double_y][target[0]].piece == Piece::Empty {
+                        moves.push([target[0], double_y]);
+                    }
+                }
+                _ => {
+                    if (target_square.piece != Piece::Empty && target_square.color != square.color)
+                        || self.en_passant == Some(target)
+                    {
+                        moves.push(target);
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    /// Where the king on `[x, y]` can castle to: the square it lands on, or in Chess960 the
+    /// square of the rook it castles with.
+    fn find_castling_destinations(&self, x: usize, y: usize) -> Vec<[usize; 2]> {
+        let mut moves = Vec::new();
+        let color = self.board[y][x].color;
+        if self.king_square_attacked(x, y, color) {
+            return moves;
+        }
+
+        for (side, (king_to, rook_to)) in CASTLED_FILES.into_iter().enumerate() {
+            if !self.castling_rights[color][side] {
+                continue;
+            }
+
+            let rook_x = self.castling_rooks[color][side];
+            if self.board[y][rook_x] != Square::new(Piece::Rook, color) {
+                continue;
+            }
+
+            // Every square the king or rook crosses or lands on must be empty, apart from
+            // the squares the two of them start on
+            let low = x.min(rook_x).min(king_to).min(rook_to);
+            let high = x.max(rook_x).max(king_to).max(rook_to);
+            if (low..=high).any(|tmp_x| {
+                tmp_x != x && tmp_x != rook_x && self.board[y][tmp_x].piece != Piece::Empty
+            }) {
+                continue;
+            }
+
+            if (x.min(king_to)..=x.max(king_to))
+                .any(|tmp_x| tmp_x != x && self.king_square_attacked(tmp_x, y, color))
+            {
+                continue;
+            }
+
+            if self.chess960 {
+                moves.push([rook_x, y]);
+            } else {
+                moves.push([king_to, y]);
+            }
+        }
+        moves
+    }
+
+    /// The squares the piece on `[x, y]` could move to, ignoring whether its king is left in
+    /// check.
+    fn find_destinations(&self, x: usize, y: usize) -> Vec<[usize; 2]> {
+        let square = self.board[y][x];
+        if square.piece == Piece::Pawn {
+            return self.find_pawn_destinations(x, y);
+        }
+
+        let range = match square.piece {
+            Piece::Queen | Piece::Rook | Piece::Bishop => 7,
+            _ => 1,
+        };
+        let mut moves: Vec<[usize; 2]> = Vec::new();
+        for m in square.moves() {
+            let change = get_change_from_move(m);
+            let mut tmp_x: isize = x as isize;
+            let mut tmp_y: isize = y as isize;
+            for _ in 0..range {
+                tmp_x += change[0];
+                tmp_y += change[1];
+
+                if !(0..=7).contains(&tmp_x) || !(0..=7).contains(&tmp_y) {
+                    break;
+                }
+
+                let target = self.board[tmp_y as usize][tmp_x as usize];
+                if target.color == square.color {
+                    break;
+                }
+
+                moves.push([tmp_x as usize, tmp_y as usize]);
+                if target.piece != Piece::Empty {
+                    break;
+                }
+            }
+        }
+
+        if square.piece == Piece::King
+            && self.variant.has_castling()
+            && y == [7, 0][square.color]
+            && self.castling_rights[square.color].contains(&true)
+        {
+            moves.append(&mut self.find_castling_destinations(x, y));
+        }
+        moves
+    }
+
+    /// Whether playing `m` would leave the mover's own king in check, or break a rule of
+    /// the variant.
+    fn leaves_king_in_check(&self, m: &ChessMove) -> bool {
+        let mut position = self.clone();
+        position.make_move(m);
+        let exposed = if !self.variant.has_royal_king() {
+            false
+        } else if !position.has_king(self.turn) {
+            // An explosion may not take the mover's own king
+            true
+        } else if !position.has_king(position.turn) {
+            // Blowing up the enemy king wins at once, whatever it leaves attacked
+            false
+        } else {
+            position.king_attacked(self.turn)
+        };
+        exposed || !self.variant.allows(&position)
+    }
+
+    /// The legal moves of the piece on `[x, y]`, one per promotion piece where relevant.
+    pub fn moves_from(&self, x: usize, y: usize) -> Vec<ChessMove> {
+        let mut moves = self.unforced_moves_from(x, y);
+        if self.variant.has_compulsory_captures()
+            && !moves.iter().all(|m| m.capture)
+            && self.can_capture()
+        {
+            moves.retain(|m| m.capture);
+        }
+        moves
+    }
+
+    /// The moves of the piece on `[x, y]` that are legal if captures are not compulsory.
+    fn unforced_moves_from(&self, x: usize, y: usize) -> Vec<ChessMove> {
+        let square = self.board[y][x];
+        let mut moves = Vec::new();
+        if square.color != self.turn {
+            return moves;
+        }
+
+        for to in self.find_destinations(x, y) {
+            let mut m = ChessMove::new([x, y], to, square.piece);
+            let target = self.board[to[1]][to[0]];
+            // A king landing on its own rook is castling in Chess960
+            m.castle = square.piece == Piece::King
+                && ((to[0] as isize - x as isize).abs() == 2 || target.color == square.color);
+            m.capture = target.piece != Piece::Empty && !m.castle;
+            if square.piece == Piece::Pawn {
+                m.double_push = (to[1] as isize - y as isize).abs() == 2;
+                m.en_passant = x != to[0] && !m.capture;
+                m.capture |= m.en_passant;
+            }
+
+            // A king cannot capture in Atomic, as it would blow itself up
+            if self.variant.has_explosions() && square.piece == Piece::King && m.capture {
+                continue;
+            }
+
+            if self.leaves_king_in_check(&m) {
+                continue;
+            }
+
+            if self.is_promotion([x, y], to) {
+                for &piece in self.variant.promotions() {
+                    moves.push(ChessMove {
+                        promotion: Some(piece),
+                        ..m
+                    });
+                }
+            } else {
+                moves.push(m);
+            }
+        }
+        moves
+    }
+
+    /// Every legal move for the side to move.
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        let mut moves = Vec::new();
+        for y in 0..8 {
+            for x in 0..8 {
+                if self.board[y][x].color == self.turn {
+                    moves.append(&mut self.unforced_moves_from(x, y));
+                }
+            }
+        }
+        if self.variant.has_compulsory_captures() && moves.iter().any(|m| m.capture) {
+            moves.retain(|m| m.capture);
+        }
+        if self.variant.has_drops() {
+            for piece in POCKET_PIECES {
+                moves.append(&mut self.drops(piece));
+            }
+        }
+        moves
+    }
+
+    /// The legal drops of `piece` from the pocket of the side to move.
+    pub fn drops(&self, piece: Piece) -> Vec<ChessMove> {
+        let mut moves = Vec::new();
+        if self.pocket(self.turn, piece) == 0 {
+            return moves;
+        }
+
+        // A drop never uncovers an attack, so only blocking a check needs looking at
+        let in_check = self.in_check();
+        for y in 0..8 {
+            // Pawns cannot be dropped on the first or last rank
+            if piece == Piece::Pawn && (y == 0 || y == 7) {
+                continue;
+            }
+            for x in 0..8 {
+                if self.board[y][x].piece != Piece::Empty {
+                    continue;
+                }
+                let m = ChessMove::drop(piece, [x, y]);
+                if !in_check || !self.leaves_king_in_check(&m) {
+                    moves.push(m);
+                }
+            }
+        }
+        moves
+    }
+
+    /// Whether the side to move has a capture, which makes it compulsory in Antichess.
+    fn can_capture(&self) -> bool {
+        (0..8).any(|y| {
+            (0..8).any(|x| {
+                self.board[y][x].color == self.turn
+                    && self.unforced_moves_from(x, y).iter().any(|m| m.capture)
+            })
+        })
+    }
+
+    /// Finds the legal move matching a UCI string such as `e2e4`, `e7e8q` or `N@f3`.
+    pub fn parse_uci_move(&self, uci: &str) -> Option<ChessMove> {
+        if let Some((piece, to)) = uci.split_once('@') {
+            let mut letters = piece.chars();
+            let piece = piece_from_letter(letters.next()?).filter(|_| letters.next().is_none())?;
+            let to = parse_square(to)?;
+            return self.drops(piece).into_iter().find(|m| m.to == to);
+        }
+        if uci.len() != 4 && uci.len() != 5 {
+            return None;
+        }
+        let from = parse_square(uci.get(0..2)?)?;
+        let to = parse_square(uci.get(2..4)?)?;
+        let promotion = match uci.chars().nth(4) {
+            Some(c) => Some(piece_from_letter(c)?),
+            None => None,
+        };
+        self.moves_from(from[0], from[1])
+            .into_iter()
+            .find(|m| m.to == to && m.promotion == promotion)
+    }
+
+    /// Whether the side to move has at least one legal move.
+    pub fn has_legal_moves(&self) -> bool {
+        // Compulsory captures only ever narrow down the moves, never leave none
+        for y in 0..8 {
+            for x in 0..8 {
+                if self.board[y][x].color == self.turn && !self.unforced_moves_from(x, y).is_empty()
+                {
+                    return true;
+                }
+            }
+        }
+        self.variant.has_drops()
+            && POCKET_PIECES
+                .iter()
+                .any(|&piece| !self.drops(piece).is_empty())
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if let Some(status) = self.variant.status(self) {
+            status
+        } else if !self.has_legal_moves() {
+            if self.in_check() {
+                GameStatus::Checkmate
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if self.has_insufficient_material() {
+            GameStatus::InsufficientMaterial
+        } else if self.halfmove_clock >= 150 {
+            GameStatus::SeventyFiveMoveRule
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    /// Whether no sequence of legal moves can end in checkmate: bare kings, a single minor
+    /// piece, or only bishops that all stand on squares of the same colour.
+    pub fn has_insufficient_material(&self) -> bool {
+        if !self.variant.has_material_draws() {
+            return false;
+        }
+        let mut minors = 0;
+        let mut knights = 0;
+        let mut bishop_squares = [false, false];
+        for y in 0..8 {
+            for x in 0..8 {
+                match self.board[y][x].piece {
+                    Piece::King | Piece::Empty => (),
+                    Piece::Knight => {
+                        minors += 1;
+                        knights += 1;
+                    }
+                    Piece::Bishop => {
+                        minors += 1;
+                        bishop_squares[(x + y) % 2] = true;
+                    }
+                    _ => return false,
+                }
+            }
+        }
+
+        minors == 0
+            || (self.variant.has_minor_piece_draws()
+                && (minors <= 1 || (knights == 0 && !(bishop_squares[0] && bishop_squares[1]))))
+    }
+
+    /// Whether `color` could still checkmate with the opponent's help, which decides if the
+    /// opponent running out of time loses or draws. A lone knight needs another enemy piece
+    /// to hem the king in, and bishops that all stand on one colour need an enemy knight,
+    /// pawn or bishop on the other colour.
+    pub fn has_mating_material(&self, color: usize) -> bool {
+        if !self.variant.has_material_draws() {
+            return true;
+        }
+        let mut knights = 0;
+        let mut bishop_squares = [false, false];
+        let mut opponent_pieces = 0;
+        let mut opponent_blockers = [false, false];
+        for y in 0..8 {
+            for x in 0..8 {
+                let square = self.board[y][x];
+                if square.color == color {
+                    match square.piece {
+                        Piece::Pawn | Piece::Rook | Piece::Queen => return true,
+                        Piece::Knight => knights += 1,
+                        Piece::Bishop => bishop_squares[(x + y) % 2] = true,
+                        _ => (),
+                    }
+                } else if square.color == 1 - color && square.piece != Piece::King {
+                    opponent_pieces += 1;
+                    match square.piece {
+                        Piece::Pawn | Piece::Knight => opponent_blockers = [true, true],
+                        Piece::Bishop => opponent_blockers[(x + y) % 2] = true,
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        let bishops = bishop_squares.iter().filter(|&&b| b).count();
+        match (knights, bishops) {
+            (0, 0) => false,
+            _ if !self.variant.has_minor_piece_draws() => true,
+            (1, 0) => opponent_pieces > 0,
+            (0, 1) => {
+                let colour = if bishop_squares[0] { 1 } else { 0 };
+                opponent_blockers[colour]
+            }
+            _ => true,
+        }
+    }
+
+    /// Identifies the position for repetition purposes. The en passant square only counts
+    /// when a pawn can actually capture there.
+    pub fn repetition_key(&self) -> RepetitionKey {
+        let en_passant = self.en_passant.filter(|&to| {
+            self.legal_moves()
+                .iter()
+                .any(|m| m.en_passant && m.to == to)
+        });
+        RepetitionKey {
+            board: self.board,
+            turn: self.turn,
+            castling_rights: self.castling_rights,
+            en_passant,
+            checks: self.checks,
+            pockets: self.pockets,
+        }
+    }
+
+    fn is_promotion(&self, from: [usize; 2], to: [usize; 2]) -> bool {
+        if self.board[from[1]][from[0]].piece != Piece::Pawn {
+            return false;
+        }
+
+        if self.turn == 0 {
+            to[1] == 0
+        } else {
+            to[1] == 7
+        }
+    }
+
+    /// Plays a move without checking its legality.
+    pub fn make_move(&mut self, m: &ChessMove) {
+        let (from, to) = (m.from, m.to);
+        let moving = self.board[from[1]][from[0]];
+
+        if self.variant.has_drops() {
+            self.pocket_capture(m);
+        }
+        self.update_halfmove_clock(m);
+        self.update_en_passant_capture(m);
+        self.update_en_passant_field(m);
+        self.update_castling_rights(from, to);
+
+        if m.drop {
+            self.pockets[self.turn][pocket_index(m.piece).unwrap()] -= 1;
+            self.place_piece(m.piece, self.turn, to[0], to[1]);
+        } else if m.castle {
+            self.castle_king(m);
+        } else {
+            let promoted = self.promoted[from[1]][from[0]] || m.promotion.is_some();
+            match m.promotion {
+                Some(piece) => self.board[to[1]][to[0]] = Square::new(piece, moving.color),
+                None => self.board[to[1]][to[0]] = moving,
+            }
+            self.empty_square(from[0], from[1]);
+            self.promoted[to[1]][to[0]] = promoted && self.variant.has_drops();
+
+            if moving.piece == Piece::King {
+                self.king_coords[self.turn] = to;
+            }
+            if m.capture && self.variant.has_explosions() {
+                self.explode(to);
+            }
+        }
+
+        self.update_turn();
+        if self.variant == Variant::ThreeCheck && self.in_check() {
+            self.checks[1 - self.turn] += 1;
+        }
+    }
+
+    // Game data helper functions
+    fn update_turn(&mut self) {
+        if self.turn == 0 {
+            self.turn = 1;
+        } else {
+            self.turn = 0;
+            self.fullmoves += 1;
+        }
+    }
+
+    /// Puts the piece `m` captures in the mover's pocket, as a pawn if it was promoted.
+    fn pocket_capture(&mut self, m: &ChessMove) {
+        if !m.capture {
+            return;
+        }
+        let [x, y] = m.to;
+        let piece = if m.en_passant || self.promoted[y][x] {
+            Piece::Pawn
+        } else {
+            self.board[y][x].piece
+        };
+        if let Some(i) = pocket_index(piece) {
+            self.pockets[self.turn][i] += 1;
+        }
+    }
+
+    fn update_en_passant_capture(&mut self, m: &ChessMove) {
+        if !m.en_passant {
+            return;
+        }
+
+        self.empty_square(m.to[0], m.from[1]);
+    }
+
+    fn update_en_passant_field(&mut self, m: &ChessMove) {
+        self.en_passant = None;
+
+        if m.double_push {
+            self.en_passant = Some([m.to[0], (m.to[1] + m.from[1]) / 2]);
+        }
+    }
+
+    fn update_castling_rights(&mut self, from: [usize; 2], to: [usize; 2]) {
+        if self.board[from[1]][from[0]].piece == Piece::King {
+            self.castling_rights[self.turn] = [false, false];
+        }
+
+        // A rook leaving or being captured on its starting square loses that side's right
+        self.lose_castling_rights_at(from);
+        self.lose_castling_rights_at(to);
+    }
+
+    fn lose_castling_rights_at(&mut self, square: [usize; 2]) {
+        for (color, y) in [(0, 7), (1, 0)] {
+            for side in 0..2 {
+                if square == [self.castling_rooks[color][side], y] {
+                    self.castling_rights[color][side] = false;
+                }
+            }
+        }
+    }
+
+    /// Blows up the piece that captured on `at`, and every piece but pawns around it.
+    fn explode(&mut self, at: [usize; 2]) {
+        self.empty_square(at[0], at[1]);
+        for y in at[1].saturating_sub(1)..=(at[1] + 1).min(7) {
+            for x in at[0].saturating_sub(1)..=(at[0] + 1).min(7) {
+                if !matches!(self.board[y][x].piece, Piece::Pawn | Piece::Empty) {
+                    self.empty_square(x, y);
+                    self.lose_castling_rights_at([x, y]);
+                }
+            }
+        }
+    }
+
+    /// Moves the king and rook to their castled squares. Both are lifted before either is
+    /// put down, as in Chess960 each may land where the other started.
+    fn castle_king(&mut self, m: &ChessMove) {
+        let side = if m.to[0] > m.from[0] { 0 } else { 1 };
+        let y = m.from[1];
+        let rook_x = self.castling_rooks[self.turn][side];
+        let (king_to, rook_to) = CASTLED_FILES[side];
+        self.empty_square(m.from[0], y);
+        self.empty_square(rook_x, y);
+        self.place_piece(Piece::King, self.turn, king_to, y);
+        self.place_piece(Piece::Rook, self.turn, rook_to, y);
+    }
+
+    fn update_halfmove_clock(&mut self, m: &ChessMove) {
+        let (from, to) = (m.from, m.to);
+        if self.board[from[1]][from[0]].piece == Piece::Pawn
+            || (self.board[to[1]][to[0]].piece != Piece::Empty && !m.castle)
+        {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+    }
+}
+
+/// Where `piece` is counted in a pocket, if it can be held in one.
+fn pocket_index(piece: Piece) -> Option<usize> {
+    POCKET_PIECES.iter().position(|&p| p == piece)
+}
+
+/// Algebraic name of a square, e.g. `e4`.
+pub fn square_name(x: usize, y: usize) -> String {
+    format!("{}{}", (b'a' + x as u8) as char, 8 - y)
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::chess_move::ChessMove;
+use crate::position::{GameStatus, Position};
+
+/// How a game ended, as written in the PGN result token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Ongoing,
+}
+
+/// A draw a player may claim but that does not end the game by itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawClaim {
+    ThreefoldRepetition,
+    FiftyMoveRule,
+}
+
+/// The moves played in a game, the position it started from and its PGN tags.
+///
+/// Every position reached is kept so moves can be taken back and replayed without
+/// recomputing the game.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    moves: Vec<ChessMove>,
+    /// `positions[i]` is the position after `i` moves.
+    positions: Vec<Position>,
+    /// The time the player had left after each move, when the game was played on a clock.
+    clocks: Vec<Option<Duration>>,
+    /// Moves taken back with `undo` and their clock times, most recent last.
+    undone: Vec<(ChessMove, Option<Duration>)>,
+    /// Tag pairs in the order they are written, starting with the Seven Tag Roster.
+    pub tags: Vec<(String, String)>,
+    /// A result decided away from the board, such as a resignation.
+    pub outcome: Option<GameResult>,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let token = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        };
+        write!(f, "{}", token)
+    }
+}
+
+impl GameResult {
+    /// Parses a PGN result token.
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Ongoing),
+            _ => None,
+        }
+    }
+}
+
+impl GameRecord {
+    pub fn new(start: Position) -> Self {
+        let tags = ["Event", "Site", "Date", "Round", "White", "Black", "Result"]
+            .into_iter()
+            .map(|name| {
+                let value = if name == "Date" { "????.??.??" } else { "?" };
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+        Self {
+            moves: Vec::new(),
+            positions: vec![start],
+            clocks: Vec::new(),
+            undone: Vec::new(),
+            tags,
+            outcome: None,
+        }
+    }
+
+    pub fn start(&self) -> &Position {
+        &self.positions[0]
+    }
+
+    pub fn moves(&self) -> &[ChessMove] {
+        &self.moves
+    }
+
+    /// The time left after each move, for games played on a clock.
+    pub fn clocks(&self) -> &[Option<Duration>] {
+        &self.clocks
+    }
+
+    /// Plays a move on the current position. Any moves that were undone can no longer be
+    /// redone.
+    pub fn push(&mut self, m: ChessMove) {
+        let mut position = self.position();
+        position.make_move(&m);
+        self.moves.push(m);
+        self.positions.push(position);
+        self.clocks.push(None);
+        self.undone.clear();
+    }
+
+    /// Records the time the player who made the last move had left after it.
+    pub fn set_clock(&mut self, remaining: Duration) {
+        if let Some(clock) = self.clocks.last_mut() {
+            *clock = Some(remaining);
+        }
+    }
+
+    /// Takes back the last move, keeping it to be redone.
+    pub fn undo(&mut self) -> Option<ChessMove> {
+        let m = self.moves.pop()?;
+        self.positions.pop();
+        let clock = self.clocks.pop().flatten();
+        self.undone.push((m, clock));
+        self.clear_outcome();
+        Some(m)
+    }
+
+    /// Plays the last move that was taken back again.
+    pub fn redo(&mut self) -> Option<ChessMove> {
+        let (m, clock) = self.undone.pop()?;
+        let mut position = self.position();
+        position.make_move(&m);
+        self.moves.push(m);
+        self.positions.push(position);
+        self.clocks.push(clock);
+        Some(m)
+    }
+
+    /// Drops every move after the first `ply`, so play can continue from there.
+    pub fn truncate(&mut self, ply: usize) {
+        if ply < self.moves.len() {
+            self.moves.truncate(ply);
+            self.positions.truncate(ply + 1);
+            self.clocks.truncate(ply);
+            self.undone.clear();
+            self.clear_outcome();
+        }
+    }
+
+    fn clear_outcome(&mut self) {
+        self.outcome = None;
+        self.tags.retain(|(name, _)| name != "Termination");
+    }
+
+    /// Ends the game with `color` resigning.
+    pub fn resign(&mut self, color: usize) {
+        self.outcome = Some(if color == 0 {
+            GameResult::BlackWins
+        } else {
+            GameResult::WhiteWins
+        });
+    }
+
+    /// Ends the game because `color` ran out of time. The opponent wins unless it has too
+    /// little material left to ever checkmate, which makes it a draw.
+    pub fn flag_fell(&mut self, color: usize) {
+        let winner = 1 - color;
+        self.outcome = Some(if !self.position().has_mating_material(winner) {
+            GameResult::Draw
+        } else if winner == 0 {
+            GameResult::WhiteWins
+        } else {
+            GameResult::BlackWins
+        });
+        self.set_tag("Termination", "time forfeit");
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a tag, replacing its value if it is already present.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// The position after every recorded move has been played.
+    pub fn position(&self) -> Position {
+        self.position_at(self.moves.len())
+    }
+
+    /// The position after the first `ply` recorded moves have been played.
+    pub fn position_at(&self, ply: usize) -> Position {
+        self.positions[ply.min(self.moves.len())].clone()
+    }
+
+    /// The SAN of every recorded move.
+    pub fn sans(&self) -> Vec<String> {
+        self.moves
+            .iter()
+            .zip(&self.positions)
+            .map(|(m, position)| position.san(m))
+            .collect()
+    }
+
+    /// How many times the current position has occurred, counting itself.
+    pub fn repetitions(&self) -> usize {
+        let key = self.position().repetition_key();
+        // Captures and pawn moves are irreversible, so only positions since the last of
+        // them can repeat
+        let reversible = self.position().halfmove_clock + 1;
+        self.positions
+            .iter()
+            .rev()
+            .take(reversible)
+            .filter(|position| position.repetition_key() == key)
+            .count()
+    }
+
+    /// The state of the game, including draws that depend on its history.
+    pub fn status(&self) -> GameStatus {
+        let status = self.position().status();
+        if status == GameStatus::Ongoing && self.repetitions() >= 5 {
+            GameStatus::FivefoldRepetition
+        } else {
+            status
+        }
+    }
+
+    /// A draw the side to move could claim now, if any.
+    pub fn claimable_draw(&self) -> Option<DrawClaim> {
+        if self.status() != GameStatus::Ongoing {
+            None
+        } else if self.repetitions() >= 3 {
+            Some(DrawClaim::ThreefoldRepetition)
+        } else if self.position().halfmove_clock >= 100 {
+            Some(DrawClaim::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    pub fn result(&self) -> GameResult {
+        if let Some(outcome) = self.outcome {
+            return outcome;
+        }
+
+        match self.status() {
+            GameStatus::Checkmate if self.position().turn == 0 => GameResult::BlackWins,
+            GameStatus::Checkmate => GameResult::WhiteWins,
+            GameStatus::VariantWin(0) => GameResult::WhiteWins,
+            GameStatus::VariantWin(_) => GameResult::BlackWins,
+            GameStatus::Ongoing => GameResult::Ongoing,
+            _ => GameResult::Draw,
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::chess_move::{parse_square, piece_from_letter, piece_letter, ChessMove};
+use crate::position::{square_name, GameStatus, Piece, Position};
+
+/// Why a move in algebraic notation could not be matched to a legal move.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// The text is not algebraic notation.
+    Invalid(String),
+    /// No legal move matches.
+    Illegal(String),
+    /// More than one legal move matches.
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "'{}' is not a move", san),
+            SanError::Illegal(san) => write!(f, "{} is not a legal move", san),
+            SanError::Ambiguous(san) => write!(f, "{} matches more than one move", san),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+impl Position {
+    /// Standard algebraic notation for a legal move, e.g. `Nbd7`, `exd6`, `O-O`, `e8=Q#` or
+    /// the drop `N@f3`.
+    pub fn san(&self, m: &ChessMove) -> String {
+        let mut san = if m.drop {
+            format!(
+                "{}@{}",
+                piece_letter(m.piece),
+                square_name(m.to[0], m.to[1])
+            )
+        } else if m.castle {
+            if m.to[0] > m.from[0] {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            }
+        } else if m.piece == Piece::Pawn {
+            let mut san = String::new();
+            if m.capture {
+                san.push((b'a' + m.from[0] as u8) as char);
+                san.push('x');
+            }
+            san += &square_name(m.to[0], m.to[1]);
+            if let Some(piece) = m.promotion {
+                san.push('=');
+                san.push(piece_letter(piece));
+            }
+            san
+        } else {
+            let mut san = piece_letter(m.piece).to_string();
+            san += &self.disambiguation(m);
+            if m.capture {
+                san.push('x');
+            }
+            san += &square_name(m.to[0], m.to[1]);
+            san
+        };
+
+        let mut next = self.clone();
+        next.make_move(m);
+        // Blowing up the king in Atomic ends the game like a mate
+        if next.in_check() || !next.has_king(next.turn) {
+            if matches!(
+                next.status(),
+                GameStatus::Checkmate | GameStatus::VariantWin(_)
+            ) {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+        san
+    }
+
+    /// The file, rank or both needed to tell `m` apart from other moves of the same piece
+    /// type to the same square.
+    fn disambiguation(&self, m: &ChessMove) -> String {
+        let others: Vec<ChessMove> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|other| {
+                other.piece == m.piece && other.to == m.to && other.from != m.from && !other.drop
+            })
+            .collect();
+        if others.is_empty() {
+            return String::new();
+        }
+
+        let file = (b'a' + m.from[0] as u8) as char;
+        let rank = (8 - m.from[1]).to_string();
+        if others.iter().all(|other| other.from[0] != m.from[0]) {
+            file.to_string()
+        } else if others.iter().all(|other| other.from[1] != m.from[1]) {
+            rank
+        } else {
+            format!("{}{}", file, rank)
+        }
+    }
+
+    /// Finds the legal move written in algebraic notation. Check and annotation suffixes are
+    /// ignored, `0-0` is accepted for castling, `@e4` for a pawn drop and extra
+    /// disambiguation such as `Ng1f3` or `e2e4` is allowed.
+    pub fn parse_san(&self, san: &str) -> Result<ChessMove, SanError> {
+        let matches = self.san_matches(san)?;
+        match matches.len() {
+            0 => Err(SanError::Illegal(san.to_string())),
+            1 => Ok(matches[0]),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+
+    /// Every legal move that the algebraic notation `san` could mean, which is more than one
+    /// when it is ambiguous.
+    pub fn san_matches(&self, san: &str) -> Result<Vec<ChessMove>, SanError> {
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+        let castle = match trimmed {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(kingside) = castle {
+            return Ok(self
+                .legal_moves()
+                .into_iter()
+                .filter(|m| m.castle && (m.to[0] > m.from[0]) == kingside)
+                .collect());
+        }
+
+        let invalid = || SanError::Invalid(san.to_string());
+        if let Some((piece, to)) = trimmed.split_once('@') {
+            let piece = match piece.chars().collect::<Vec<char>>().as_slice() {
+                [] => Piece::Pawn,
+                [c] if c.is_ascii_uppercase() => piece_from_letter(*c).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            };
+            let to = parse_square(to).ok_or_else(invalid)?;
+            return Ok(self
+                .legal_moves()
+                .into_iter()
+                .filter(|m| m.drop && m.piece == piece && m.to == to)
+                .collect());
+        }
+
+        let mut chars: Vec<char> = trimmed.chars().collect();
+
+        let mut promotion = None;
+        if let Some(&last) = chars.last() {
+            if last.is_ascii_alphabetic() {
+                promotion = Some(piece_from_letter(last).ok_or_else(invalid)?);
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(invalid());
+        }
+        let to: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to = parse_square(&to).ok_or_else(invalid)?;
+
+        let piece = match chars.first() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let piece = piece_from_letter(*c).ok_or_else(invalid)?;
+                chars.remove(0);
+                piece
+            }
+            _ => Piece::Pawn,
+        };
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in chars {
+            match c {
+                'a'..='h' => from_file = Some(c as usize - 'a' as usize),
+                '1'..='8' => from_rank = Some(8 - (c as usize - '0' as usize)),
+                'x' | '-' => (),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(self
+            .legal_moves()
+            .into_iter()
+            .filter(|m| {
+                m.piece == piece
+                    && m.to == to
+                    && !m.drop
+                    && m.promotion == promotion
+                    && from_file.is_none_or(|x| m.from[0] == x)
+                    && from_rank.is_none_or(|y| m.from[1] == y)
+            })
+            .collect())
+    }
+
+    /// Finds the legal move typed as `text`, in UCI notation such as `e1g1` or `e7e8q`, or
+    /// in algebraic notation.
+    pub fn parse_move(&self, text: &str) -> Result<ChessMove, SanError> {
+        match self.parse_uci_move(text) {
+            Some(m) => Ok(m),
+            None => self.parse_san(text),
+        }
+    }
+
+    /// The legal moves in algebraic notation that start with `prefix`, or in UCI notation
+    /// when none do, in alphabetical order.
+    pub fn complete_move(&self, prefix: &str) -> Vec<String> {
+        let moves = self.legal_moves();
+        let matching = |notation: &dyn Fn(&ChessMove) -> String| {
+            let mut completions: Vec<String> = moves
+                .iter()
+                .map(notation)
+                .filter(|text| text.starts_with(prefix))
+                .collect();
+            completions.sort();
+            completions.dedup();
+            completions
+        };
+        let completions = matching(&|m| self.san(m));
+        if completions.is_empty() {
+            matching(&|m| m.to_uci())
+        } else {
+            completions
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::chess_move::ChessMove;
+use crate::position::{GameStatus, Piece, Position, POCKET_PIECES};
+use crate::variant::Variant;
+
+/// The score of delivering checkmate right away. Mates further off score a little less
+/// for every ply they take.
+pub const MATE: i32 = 100_000;
+const INFINITY: i32 = MATE + 1;
+/// The deepest search iterative deepening will start without a depth limit.
+const MAX_DEPTH: usize = 64;
+
+/// How long the search may run before it has to answer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// Search every line this many plies deep.
+    Depth(usize),
+    /// Deepen the search until this much time has passed.
+    Time(Duration),
+    /// Deepen the search until it has visited about this many nodes.
+    Nodes(u64),
+    /// Deepen the search until it is stopped.
+    Infinite,
+}
+
+/// What the search found after its deepest completed iteration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: usize,
+    /// Centipawns from the point of view of the side to move.
+    pub score: i32,
+    pub nodes: u64,
+    /// The line the search expects to be played, best move first.
+    pub pv: Vec<ChessMove>,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<ChessMove> {
+        self.pv.first().copied()
+    }
+
+    /// Moves until mate if the score is a forced mate, negative when the side to move is
+    /// the one getting mated.
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() < MATE - MAX_DEPTH as i32 * 2 {
+            return None;
+        }
+        let plies = MATE - self.score.abs();
+        let moves = (plies + 1) / 2;
+        Some(if self.score > 0 { moves } else { -moves })
+    }
+}
+
+/// Finds the best move for the side to move.
+pub fn search(position: &Position, limit: Limit) -> SearchInfo {
+    search_with(position, limit, &AtomicBool::new(false), |_| ())
+}
+
+/// Finds the best move with iterative deepening, calling `report` after every completed
+/// depth. Setting `stop` ends the search early with the deepest result found so far.
+pub fn search_with(
+    position: &Position,
+    limit: Limit,
+    stop: &AtomicBool,
+    mut report: impl FnMut(&SearchInfo),
+) -> SearchInfo {
+    let (max_depth, deadline, max_nodes) = match limit {
+        Limit::Depth(depth) => (depth.max(1), None, None),
+        Limit::Time(time) => (MAX_DEPTH, Some(Instant::now() + time), None),
+        Limit::Nodes(nodes) => (MAX_DEPTH, None, Some(nodes)),
+        Limit::Infinite => (MAX_DEPTH, None, None),
+    };
+    let mut searcher = Searcher {
+        deadline,
+        max_nodes,
+        stop,
+        nodes: 0,
+        can_stop: false,
+        aborted: false,
+    };
+
+    let mut best = SearchInfo {
+        depth: 0,
+        score: evaluate(position),
+        nodes: 0,
+        pv: Vec::new(),
+    };
+    for depth in 1..=max_depth {
+        // The first iteration always finishes so there is a move to play
+        searcher.can_stop = depth > 1;
+        let mut pv = Vec::new();
+        let score = searcher.negamax(position, depth, 0, -INFINITY, INFINITY, &best.pv, &mut pv);
+        if searcher.aborted {
+            break;
+        }
+
+        best = SearchInfo {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            pv,
+        };
+        report(&best);
+        if best.pv.is_empty()
+            || best
+                .mate_in()
+                .is_some_and(|n| n.unsigned_abs() as usize * 2 <= depth)
+        {
+            break;
+        }
+    }
+    best
+}
+
+struct Searcher<'a> {
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    stop: &'a AtomicBool,
+    nodes: u64,
+    /// Whether the current iteration may be cut short.
+    can_stop: bool,
+    aborted: bool,
+}
+
+impl Searcher<'_> {
+    fn out_of_time(&mut self) -> bool {
+        if self.can_stop && !self.aborted {
+            // Reading the clock is slow compared to a node, so only look at it now and then
+            self.aborted = self.stop.load(Ordering::Relaxed)
+                || self.max_nodes.is_some_and(|max| self.nodes >= max)
+                || (self.nodes.is_multiple_of(1024)
+                    && self
+                        .deadline
+                        .is_some_and(|deadline| Instant::now() >= deadline));
+        }
+        self.aborted
+    }
+
+    /// Alpha-beta search `depth` plies deep. `follow` is the principal variation of the
+    /// previous iteration from this node on, whose move is tried first.
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        position: &Position,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        follow: &[ChessMove],
+        pv: &mut Vec<ChessMove>,
+    ) -> i32 {
+        self.nodes += 1;
+        if ply > 0 && self.out_of_time() {
+            return 0;
+        }
+
+        if let Some(score) = variant_score(position, ply) {
+            return score;
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.in_check() {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+        if ply > 0 && (position.halfmove_clock >= 100 || position.has_insufficient_material()) {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+
+        order_moves(position, &mut moves, follow.first());
+        for m in moves {
+            let mut next = position.clone();
+            next.make_move(&m);
+            let rest = match follow.split_first() {
+                Some((first, rest)) if *first == m => rest,
+                _ => &[],
+            };
+            let mut line = Vec::new();
+            let score = -self.negamax(&next, depth - 1, ply + 1, -beta, -alpha, rest, &mut line);
+            if self.aborted {
+                return 0;
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(m);
+                pv.append(&mut line);
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        alpha
+    }
+
+    /// Searches captures and promotions until the position is quiet, so the evaluation is
+    /// never taken in the middle of an exchange.
+    fn quiescence(&mut self, position: &Position, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return 0;
+        }
+        // A capture can end the game, such as one that blows up a king in Atomic
+        if let Some(score) = variant_score(position, ply) {
+            return score;
+        }
+
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<ChessMove> = position
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.capture || m.promotion == Some(Piece::Queen))
+            .collect();
+        order_moves(position, &mut moves, None);
+        for m in moves {
+            let mut next = position.clone();
+            next.make_move(&m);
+            let score = -self.quiescence(&next, ply + 1, -beta, -alpha);
+            if self.aborted {
+                return 0;
+            }
+
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+/// The score of a game the variant's own rules have ended, `ply` moves into the search.
+fn variant_score(position: &Position, ply: usize) -> Option<i32> {
+    if position.variant == Variant::Standard {
+        return None;
+    }
+    match position.variant.status(position)? {
+        GameStatus::VariantWin(color) if color == position.turn => Some(MATE - ply as i32),
+        GameStatus::VariantWin(_) => Some(-MATE + ply as i32),
+        _ => Some(0),
+    }
+}
+
+/// Puts the move from the previous iteration first, then captures of the most valuable
+/// pieces by the least valuable ones, then promotions.
+fn order_moves(position: &Position, moves: &mut [ChessMove], first: Option<&ChessMove>) {
+    moves.sort_by_cached_key(|m| {
+        if Some(m) == first {
+            return i32::MIN;
+        }
+        let mut key = 0;
+        if m.capture {
+            let victim = if m.en_passant {
+                Piece::Pawn
+            } else {
+                position.square(m.to[0], m.to[1]).piece
+            };
+            key -= 10 * piece_value(victim) - piece_value(m.piece) / 10;
+        }
+        if let Some(piece) = m.promotion {
+            key -= piece_value(piece);
+        }
+        key
+    });
+}
+
+fn piece_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 100,
+        Piece::Knight => 320,
+        Piece::Bishop => 330,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        Piece::King | Piece::Empty => 0,
+    }
+}
+
+// Piece-square tables from White's side, indexed [y][x] with rank 8 first
+#[rustfmt::skip]
+const PAWN_TABLE: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [ 50,  50,  50,  50,  50,  50,  50,  50],
+    [ 10,  10,  20,  30,  30,  20,  10,  10],
+    [  5,   5,  10,  25,  25,  10,   5,   5],
+    [  0,   0,   0,  20,  20,   0,   0,   0],
+    [  5,  -5, -10,   0,   0, -10,  -5,   5],
+    [  5,  10,  10, -20, -20,  10,  10,   5],
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [[i32; 8]; 8] = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20,   0,   0,   0,   0, -20, -40],
+    [-30,   0,  10,  15,  15,  10,   0, -30],
+    [-30,   5,  15,  20,  20,  15,   5, -30],
+    [-30,   0,  15,  20,  20,  15,   0, -30],
+    [-30,   5,  10,  15,  15,  10,   5, -30],
+    [-40, -20,   0,   5,   5,   0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [[i32; 8]; 8] = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,  10,  10,   5,   0, -10],
+    [-10,   5,   5,  10,  10,   5,   5, -10],
+    [-10,   0,  10,  10,  10,  10,   0, -10],
+    [-10,  10,  10,  10,  10,  10,  10, -10],
+    [-10,   5,   0,   0,   0,   0,   5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [  5,  10,  10,  10,  10,  10,  10,   5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [  0,   0,   0,   5,   5,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [[i32; 8]; 8] = [
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,   5,   5,   5,   0, -10],
+    [ -5,   0,   5,   5,   5,   5,   0,  -5],
+    [  0,   0,   5,   5,   5,   5,   0,  -5],
+    [-10,   5,   5,   5,   5,   5,   0, -10],
+    [-10,   0,   5,   0,   0,   0,   0, -10],
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [[i32; 8]; 8] = [
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [ 20,  20,   0,   0,   0,   0,  20,  20],
+    [ 20,  30,  10,   0,   0,  10,  30,  20],
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [[i32; 8]; 8] = [
+    [-50, -40, -30, -20, -20, -30, -40, -50],
+    [-30, -20, -10,   0,   0, -10, -20, -30],
+    [-30, -10,  20,  30,  30,  20, -10, -30],
+    [-30, -10,  30,  40,  40,  30, -10, -30],
+    [-30, -10,  30,  40,  40,  30, -10, -30],
+    [-30, -10,  20,  30,  30,  20, -10, -30],
+    [-30, -30,   0,   0,   0,   0, -30, -30],
+    [-50, -30, -30, -30, -30, -30, -30, -50],
+];
+
+/// Material plus piece-square bonuses in centipawns, from the point of view of the side to
+/// move.
+pub fn evaluate(position: &Position) -> i32 {
+    let mut queens = 0;
+    let mut officers = 0;
+    for row in &position.board {
+        for square in row {
+            match square.piece {
+                Piece::Queen => queens += 1,
+                Piece::Rook | Piece::Bishop | Piece::Knight => officers += 1,
+                _ => (),
+            }
+        }
+    }
+    // Once the queens are gone or little else is left the king should walk to the centre
+    let endgame = queens == 0 || queens + officers <= 4;
+
+    let mut score = 0;
+    for (y, row) in position.board.iter().enumerate() {
+        for (x, square) in row.iter().enumerate() {
+            let table = match square.piece {
+                Piece::Pawn => &PAWN_TABLE,
+                Piece::Knight => &KNIGHT_TABLE,
+                Piece::Bishop => &BISHOP_TABLE,
+                Piece::Rook => &ROOK_TABLE,
+                Piece::Queen => &QUEEN_TABLE,
+                Piece::King if endgame => &KING_ENDGAME_TABLE,
+                Piece::King => &KING_TABLE,
+                Piece::Empty => continue,
+            };
+            let table_y = if square.color == 0 { y } else { 7 - y };
+            let value = piece_value(square.piece) + table[table_y][x];
+            if square.color == position.turn {
+                score += value;
+            } else {
+                score -= value;
+            }
+        }
+    }
+    score += variant_bonus(position, position.turn) - variant_bonus(position, 1 - position.turn);
+    // In Antichess every piece is a burden
+    if position.variant == Variant::Antichess {
+        -score
+    } else {
+        score
+    }
+}
+
+/// Progress `color` has made towards winning the variant's own way, or in Crazyhouse the
+/// pieces it holds ready to drop.
+fn variant_bonus(position: &Position, color: usize) -> i32 {
+    let [x, y] = position.king_coords[color].map(|n| n as i32);
+    match position.variant {
+        Variant::Standard | Variant::Atomic | Variant::Antichess => 0,
+        Variant::KingOfTheHill => {
+            let distance = (2 * x - 7).abs().max((2 * y - 7).abs()) / 2;
+            -40 * distance
+        }
+        Variant::ThreeCheck => 150 * position.checks[color] as i32,
+        // Both kings race towards rank 8
+        Variant::RacingKings => 80 * (7 - y),
+        Variant::Crazyhouse => POCKET_PIECES
+            .iter()
+            .map(|&piece| piece_value(piece) * position.pocket(color, piece) as i32)
+            .sum(),
+    }
+}
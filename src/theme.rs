@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// The theme used when neither the command line nor the config file picks one.
+pub const DEFAULT_THEME: &str = "classic";
+
+/// A 24-bit colour, written `#rrggbb` in the config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("'{}' is not a colour, expected #rrggbb", s);
+        let hex = s.strip_prefix('#').ok_or_else(error)?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(error());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| error());
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl Rgb {
+    /// The nearest colour of the 256-colour palette: its 6×6×6 cube or grey ramp.
+    pub fn ansi256(self) -> u8 {
+        let Rgb(r, g, b) = self;
+        if r == g && g == b {
+            return match r {
+                0..=7 => 16,
+                249..=255 => 231,
+                _ => 232 + ((r as u16 - 8) * 24 / 247) as u8,
+            };
+        }
+        let level = |v: u8| match v {
+            0..=47 => 0,
+            48..=114 => 1,
+            _ => (v - 35) / 40,
+        };
+        16 + 36 * level(r) + 6 * level(g) + level(b)
+    }
+
+    /// The nearest of the 16 basic terminal colours, as xterm shows them.
+    pub fn ansi16(self) -> u8 {
+        const BASIC: [(u8, u8, u8); 16] = [
+            (0, 0, 0),
+            (205, 0, 0),
+            (0, 205, 0),
+            (205, 205, 0),
+            (0, 0, 238),
+            (205, 0, 205),
+            (0, 205, 205),
+            (229, 229, 229),
+            (127, 127, 127),
+            (255, 0, 0),
+            (0, 255, 0),
+            (255, 255, 0),
+            (92, 92, 255),
+            (255, 0, 255),
+            (0, 255, 255),
+            (255, 255, 255),
+        ];
+        let distance = |(r, g, b): (u8, u8, u8)| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(r, self.0) + d(g, self.1) + d(b, self.2)
+        };
+        (0..16)
+            .min_by_key(|&i| distance(BASIC[i as usize]))
+            .unwrap()
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// The colours the board is drawn in. Colours a theme in the config file leaves out are
+/// taken from the default theme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub light_square: Rgb,
+    pub dark_square: Rgb,
+    /// The selected piece and the squares it can move to.
+    pub highlight: Rgb,
+    /// Both squares of the last move.
+    pub last_move: Rgb,
+    /// The square of a king in check.
+    pub check: Rgb,
+    /// The bars with the rank and file labels.
+    pub label: Rgb,
+    pub label_text: Rgb,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        builtin_theme(DEFAULT_THEME).unwrap()
+    }
+}
+
+/// Names of the themes that are always available.
+pub const BUILTIN_THEMES: [&str; 4] = ["classic", "wood", "ocean", "mono"];
+
+pub fn builtin_theme(name: &str) -> Option<Theme> {
+    let theme = match name {
+        "classic" => Theme {
+            light_square: Rgb(200, 200, 200),
+            dark_square: Rgb(85, 255, 85),
+            highlight: Rgb(200, 100, 0),
+            last_move: Rgb(220, 220, 120),
+            check: Rgb(220, 50, 50),
+            label: Rgb(0, 0, 205),
+            label_text: Rgb(255, 255, 255),
+        },
+        "wood" => Theme {
+            light_square: Rgb(240, 217, 181),
+            dark_square: Rgb(181, 136, 99),
+            highlight: Rgb(120, 160, 90),
+            last_move: Rgb(205, 210, 106),
+            check: Rgb(230, 60, 50),
+            label: Rgb(90, 60, 35),
+            label_text: Rgb(240, 217, 181),
+        },
+        "ocean" => Theme {
+            light_square: Rgb(222, 227, 230),
+            dark_square: Rgb(140, 162, 173),
+            highlight: Rgb(90, 150, 220),
+            last_move: Rgb(170, 210, 160),
+            check: Rgb(220, 70, 70),
+            label: Rgb(40, 60, 90),
+            label_text: Rgb(222, 227, 230),
+        },
+        "mono" => Theme {
+            light_square: Rgb(220, 220, 220),
+            dark_square: Rgb(140, 140, 140),
+            highlight: Rgb(90, 90, 90),
+            last_move: Rgb(180, 180, 130),
+            check: Rgb(200, 80, 80),
+            label: Rgb(40, 40, 40),
+            label_text: Rgb(220, 220, 220),
+        },
+        _ => return None,
+    };
+    Some(theme)
+}
+
+/// Why the config file could not be used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    Io(String),
+    Parse(String),
+    UnknownTheme(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) | ConfigError::Parse(e) => write!(f, "{}", e),
+            ConfigError::UnknownTheme(name) => write!(f, "there is no theme called '{}'", name),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Settings read from the config file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The theme to use when `--theme` is not given.
+    pub theme: Option<String>,
+    /// Themes defined in the file, which take precedence over built-in ones.
+    pub themes: BTreeMap<String, Theme>,
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|e| ConfigError::Parse(e.to_string().trim_end().to_string()))
+    }
+}
+
+impl Config {
+    /// Where the config file is looked for: `$XDG_CONFIG_HOME/chess-term/config.toml`,
+    /// falling back to `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(dir.join("chess-term").join("config.toml"))
+    }
+
+    /// Reads the config file at `path`. A missing file gives the default config unless
+    /// `required` is set.
+    pub fn load(path: &Path, required: bool) -> Result<Self, ConfigError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(ConfigError::Io(format!("{}: {}", path.display(), e))),
+        };
+        text.parse().map_err(|e| match e {
+            ConfigError::Parse(e) => ConfigError::Parse(format!("{}: {}", path.display(), e)),
+            e => e,
+        })
+    }
+
+    /// Looks up the theme called `name`, or the one the config picks without a name.
+    pub fn theme(&self, name: Option<&str>) -> Result<Theme, ConfigError> {
+        let name = name.or(self.theme.as_deref()).unwrap_or(DEFAULT_THEME);
+        self.themes
+            .get(name)
+            .copied()
+            .or_else(|| builtin_theme(name))
+            .ok_or_else(|| ConfigError::UnknownTheme(name.to_string()))
+    }
+}
+
+/// The colours a terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    /// No colour at all, as asked for by `NO_COLOR`.
+    NoColor,
+}
+
+impl Palette {
+    /// Works out the palette from the environment.
+    pub fn detect() -> Self {
+        Self::detect_with(|name| env::var(name).ok())
+    }
+
+    /// Works out the palette from environment variables looked up with `var`: `NO_COLOR`
+    /// turns colour off, `COLORTERM` tells of 24-bit colour and `TERM` of 256 colours.
+    pub fn detect_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |name: &str| var(name).filter(|value| !value.is_empty());
+        if set("NO_COLOR").is_some() {
+            return Palette::NoColor;
+        }
+        if let Some("truecolor" | "24bit") = set("COLORTERM").as_deref() {
+            return Palette::TrueColor;
+        }
+        match set("TERM") {
+            Some(term) if term.contains("256color") => Palette::Ansi256,
+            Some(term) if term == "dumb" => Palette::NoColor,
+            _ => Palette::Ansi16,
+        }
+    }
+
+    /// The escape sequence setting the background to `colour`, or nothing without colour.
+    pub fn bg(self, colour: Rgb) -> String {
+        match self {
+            Palette::TrueColor => format!("\x1b[48;2;{};{};{}m", colour.0, colour.1, colour.2),
+            Palette::Ansi256 => format!("\x1b[48;5;{}m", colour.ansi256()),
+            Palette::Ansi16 => match colour.ansi16() {
+                n @ 0..=7 => format!("\x1b[{}m", 40 + n),
+                n => format!("\x1b[{}m", 100 + n - 8),
+            },
+            Palette::NoColor => String::new(),
+        }
+    }
+
+    /// The escape sequence setting the text colour to `colour`, or nothing without colour.
+    pub fn fg(self, colour: Rgb) -> String {
+        match self {
+            Palette::TrueColor => format!("\x1b[38;2;{};{};{}m", colour.0, colour.1, colour.2),
+            Palette::Ansi256 => format!("\x1b[38;5;{}m", colour.ansi256()),
+            Palette::Ansi16 => match colour.ansi16() {
+                n @ 0..=7 => format!("\x1b[{}m", 30 + n),
+                n => format!("\x1b[{}m", 90 + n - 8),
+            },
+            Palette::NoColor => String::new(),
+        }
+    }
+}
+
+/// How pieces are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PieceSet {
+    /// The chess symbols ♔ to ♟.
+    Unicode,
+    /// Letters, upper case for White and lower case for Black.
+    Ascii,
+}
+
+impl PieceSet {
+    pub fn detect() -> Self {
+        Self::detect_with(|name| env::var(name).ok())
+    }
+
+    /// Picks Unicode symbols when the locale, the first of `LC_ALL`, `LC_CTYPE` and `LANG`
+    /// that is set, uses UTF-8.
+    pub fn detect_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| var(name).filter(|value| !value.is_empty()))
+            .unwrap_or_default()
+            .to_lowercase();
+        if locale.contains("utf-8") || locale.contains("utf8") {
+            PieceSet::Unicode
+        } else {
+            PieceSet::Ascii
+        }
+    }
+}
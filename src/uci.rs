@@ -0,0 +1,259 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::clock::time_for_move;
+use crate::position::Position;
+use crate::search::{search_with, Limit, SearchInfo};
+use crate::variant::Variant;
+
+/// Speaks the UCI protocol, reading commands from `input` and answering on `output`, until
+/// `quit` or the end of the input.
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) {
+    let mut server = Server {
+        output: Arc::new(Mutex::new(output)),
+        position: Position::new(),
+        chess960: false,
+        variant: Variant::Standard,
+        search: None,
+    };
+    for line in input.lines().map_while(Result::ok) {
+        if !server.handle(&line) {
+            server.stop_search();
+            return;
+        }
+    }
+    server.finish_search();
+}
+
+struct Server<W> {
+    output: Arc<Mutex<W>>,
+    position: Position,
+    /// Whether the GUI asked for Chess960 castling moves, given as the king taking its rook.
+    chess960: bool,
+    variant: Variant,
+    search: Option<RunningSearch>,
+}
+
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    limit: Limit,
+    thread: JoinHandle<()>,
+}
+
+fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap();
+    // A GUI that went away cannot be told anything, so write errors are ignored
+    let _ = writeln!(output, "{}", line).and_then(|_| output.flush());
+}
+
+impl<W: Write + Send + 'static> Server<W> {
+    /// Carries out one command, returning false on `quit`. Unknown commands are ignored as
+    /// the protocol asks.
+    fn handle(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = words.split_first() else {
+            return true;
+        };
+
+        match command {
+            "uci" => {
+                send(
+                    &self.output,
+                    &format!("id name chess-term {}", env!("CARGO_PKG_VERSION")),
+                );
+                send(
+                    &self.output,
+                    &format!("id author {}", env!("CARGO_PKG_AUTHORS")),
+                );
+                send(
+                    &self.output,
+                    "option name UCI_Chess960 type check default false",
+                );
+                let variants: Vec<String> = Variant::ALL
+                    .iter()
+                    .map(|variant| format!("var {}", variant.key()))
+                    .collect();
+                send(
+                    &self.output,
+                    &format!(
+                        "option name UCI_Variant type combo default chess {}",
+                        variants.join(" ")
+                    ),
+                );
+                send(&self.output, "uciok");
+            }
+            "isready" => send(&self.output, "readyok"),
+            "setoption" => self.set_option(args),
+            "ucinewgame" => {
+                self.finish_search();
+                self.position = self.variant.start();
+            }
+            // A running search has its own copy of the position, so it carries on
+            "position" => match parse_position(args, self.variant, self.chess960) {
+                Ok(position) => self.position = position,
+                Err(e) => send(&self.output, &format!("info string {}", e)),
+            },
+            "go" => {
+                self.finish_search();
+                self.go(parse_go(args, self.position.turn));
+            }
+            "stop" => self.stop_search(),
+            "quit" => return false,
+            _ => (),
+        }
+        true
+    }
+
+    /// Handles `setoption name <name> value <value>` for `UCI_Chess960` and `UCI_Variant`.
+    fn set_option(&mut self, args: &[&str]) {
+        if let ["name", name, "value", value] = args {
+            if name.eq_ignore_ascii_case("UCI_Chess960") {
+                self.chess960 = *value == "true";
+            } else if name.eq_ignore_ascii_case("UCI_Variant") {
+                match value.parse() {
+                    Ok(variant) => self.variant = variant,
+                    Err(e) => send(&self.output, &format!("info string {}", e)),
+                }
+            }
+        }
+    }
+
+    /// Searches the current position on another thread so `stop` can still be read.
+    fn go(&mut self, limit: Limit) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let position = self.position.clone();
+        let output = Arc::clone(&self.output);
+        let thread_stop = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            let started = Instant::now();
+            let info = search_with(&position, limit, &thread_stop, |info| {
+                send(&output, &info_line(info, started.elapsed()));
+            });
+            // An infinite search only answers once it is told to stop
+            if limit == Limit::Infinite {
+                while !thread_stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(5));
+                }
+            }
+            let best = info.best_move().map_or("0000".to_string(), |m| m.to_uci());
+            send(&output, &format!("bestmove {}", best));
+        });
+        self.search = Some(RunningSearch {
+            stop,
+            limit,
+            thread,
+        });
+    }
+
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            let _ = search.thread.join();
+        }
+    }
+
+    /// Waits for a search with a limit to end on its own, and stops one without.
+    fn finish_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            if search.limit == Limit::Infinite {
+                search.stop.store(true, Ordering::Relaxed);
+            }
+            let _ = search.thread.join();
+        }
+    }
+}
+
+/// Parses the arguments of `position`: `startpos` or `fen <fen>`, then optionally `moves`
+/// followed by moves in UCI notation. In Chess960 mode castling moves are given as the king
+/// taking its own rook.
+fn parse_position(args: &[&str], variant: Variant, chess960: bool) -> Result<Position, String> {
+    let moves_at = args.iter().position(|&arg| arg == "moves");
+    let (setup, moves) = match moves_at {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, &[][..]),
+    };
+
+    let mut position = match setup.split_first() {
+        Some((&"startpos", [])) => variant.start(),
+        Some((&"fen", fen)) => {
+            Position::from_variant_fen(&fen.join(" "), variant).map_err(|e| e.to_string())?
+        }
+        _ => {
+            return Err(format!(
+                "cannot set up a position from '{}'",
+                setup.join(" ")
+            ))
+        }
+    };
+    position.chess960 |= chess960;
+    for uci in moves {
+        let m = position
+            .parse_uci_move(uci)
+            .ok_or(format!("{} is not a legal move", uci))?;
+        position.make_move(&m);
+    }
+    Ok(position)
+}
+
+/// Picks the search limit for the arguments of `go`. A fixed depth, or a mate in so many
+/// moves, wins over a node count, which wins over a fixed time, which wins over the clock;
+/// with none of them the search runs until stopped.
+fn parse_go(args: &[&str], turn: usize) -> Limit {
+    let value = |name: &str| {
+        args.iter()
+            .position(|&arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .and_then(|n| n.parse::<i64>().ok())
+            // Clocks can run a little negative in some GUIs
+            .map(|n| n.max(0) as u64)
+    };
+
+    let (time, increment) = if turn == 0 {
+        (value("wtime"), value("winc"))
+    } else {
+        (value("btime"), value("binc"))
+    };
+
+    if let Some(depth) = value("depth") {
+        Limit::Depth(depth as usize)
+    } else if let Some(moves) = value("mate") {
+        Limit::Depth(2 * moves as usize)
+    } else if let Some(nodes) = value("nodes") {
+        Limit::Nodes(nodes)
+    } else if let Some(time) = value("movetime") {
+        Limit::Time(Duration::from_millis(time))
+    } else if let Some(time) = time {
+        Limit::Time(time_for_move(
+            Duration::from_millis(time),
+            Duration::from_millis(increment.unwrap_or(0)),
+            value("movestogo"),
+        ))
+    } else {
+        Limit::Infinite
+    }
+}
+
+fn info_line(info: &SearchInfo, elapsed: Duration) -> String {
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let mut line = format!(
+        "info depth {} score {} nodes {} time {}",
+        info.depth,
+        score,
+        info.nodes,
+        elapsed.as_millis()
+    );
+    if !info.pv.is_empty() {
+        line += " pv";
+        for m in &info.pv {
+            line.push(' ');
+            line += &m.to_uci();
+        }
+    }
+    line
+}
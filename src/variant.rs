@@ -0,0 +1,242 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::position::{GameStatus, Piece, Position};
+
+/// The rules a game is played by. Everything not decided here follows standard chess.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Moving your king to d4, e4, d5 or e5 also wins.
+    KingOfTheHill,
+    /// Giving check for the third time also wins.
+    ThreeCheck,
+    /// No pawns and no checks: the first king to reach the eighth rank wins.
+    RacingKings,
+    /// Captures explode, taking every piece but pawns next to the capture with them, and
+    /// blowing up the enemy king wins. Kings cannot capture.
+    Atomic,
+    /// Captures must be made, the king is an ordinary piece, and losing every piece or
+    /// having no move wins.
+    Antichess,
+    /// Captured pieces go to the capturer's pocket, and may be dropped back on the board
+    /// instead of moving.
+    Crazyhouse,
+}
+
+/// The checks that win a game of Three-check.
+pub const CHECKS_TO_WIN: usize = 3;
+
+const RACING_KINGS_START: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+
+impl Variant {
+    pub const ALL: [Variant; 7] = [
+        Variant::Standard,
+        Variant::KingOfTheHill,
+        Variant::ThreeCheck,
+        Variant::RacingKings,
+        Variant::Atomic,
+        Variant::Antichess,
+        Variant::Crazyhouse,
+    ];
+
+    /// The name used on the command line, and by UCI engines for the `UCI_Variant` option.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Variant::Standard => "chess",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::ThreeCheck => "3check",
+            Variant::RacingKings => "racingkings",
+            Variant::Atomic => "atomic",
+            Variant::Antichess => "antichess",
+            Variant::Crazyhouse => "crazyhouse",
+        }
+    }
+
+    /// The position every game of the variant starts from.
+    pub fn start(&self) -> Position {
+        let mut position = match self {
+            Variant::RacingKings => Position::from_fen(RACING_KINGS_START).unwrap(),
+            _ => Position::new(),
+        };
+        position.variant = *self;
+        if !self.has_castling() {
+            position.castling_rights = [[false, false], [false, false]];
+        }
+        position
+    }
+
+    /// Whether the variant allows castling.
+    pub fn has_castling(&self) -> bool {
+        !matches!(self, Variant::RacingKings | Variant::Antichess)
+    }
+
+    /// Whether each side has exactly one king, which must be kept out of check.
+    pub fn has_royal_king(&self) -> bool {
+        *self != Variant::Antichess
+    }
+
+    /// Whether a side that can capture must.
+    pub fn has_compulsory_captures(&self) -> bool {
+        *self == Variant::Antichess
+    }
+
+    /// Whether captures explode.
+    pub fn has_explosions(&self) -> bool {
+        *self == Variant::Atomic
+    }
+
+    /// Whether captured pieces can be dropped back on the board.
+    pub fn has_drops(&self) -> bool {
+        *self == Variant::Crazyhouse
+    }
+
+    /// The pieces a pawn may promote to.
+    pub fn promotions(&self) -> &'static [Piece] {
+        match self {
+            Variant::Antichess => &[
+                Piece::Queen,
+                Piece::Rook,
+                Piece::Bishop,
+                Piece::Knight,
+                Piece::King,
+            ],
+            _ => &[Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight],
+        }
+    }
+
+    /// Whether a lack of material can draw the game. It never can when a bare king can
+    /// still win by walking somewhere, or captured pieces come back.
+    pub fn has_material_draws(&self) -> bool {
+        !matches!(
+            self,
+            Variant::KingOfTheHill
+                | Variant::RacingKings
+                | Variant::Antichess
+                | Variant::Crazyhouse
+        )
+    }
+
+    /// Whether a single minor piece, or bishops all on one colour, count as too little
+    /// material. In Three-check they can still give the checks, so only bare kings do.
+    pub fn has_minor_piece_draws(&self) -> bool {
+        self.has_material_draws() && *self != Variant::ThreeCheck
+    }
+
+    /// Whether a move that reached `position` is allowed, on top of it not leaving the
+    /// mover's king in check.
+    pub fn allows(&self, position: &Position) -> bool {
+        match self {
+            // Neither side may give check
+            Variant::RacingKings => !position.in_check(),
+            _ => true,
+        }
+    }
+
+    /// How the game stands under the variant's own rules, or `None` when they have not
+    /// ended it and the standard ones apply.
+    pub fn status(&self, position: &Position) -> Option<GameStatus> {
+        let mover = 1 - position.turn;
+        match self {
+            Variant::Standard | Variant::Crazyhouse => None,
+            Variant::KingOfTheHill => {
+                let [x, y] = position.king_coords[mover];
+                ((3..=4).contains(&x) && (3..=4).contains(&y))
+                    .then_some(GameStatus::VariantWin(mover))
+            }
+            Variant::ThreeCheck => {
+                (position.checks[mover] >= CHECKS_TO_WIN).then_some(GameStatus::VariantWin(mover))
+            }
+            Variant::RacingKings => {
+                let home = |color: usize| position.king_coords[color][1] == 0;
+                match (home(0), home(1)) {
+                    (true, true) => Some(GameStatus::VariantDraw),
+                    (false, true) => Some(GameStatus::VariantWin(1)),
+                    // Black gets one move to draw by reaching the eighth rank too
+                    (true, false) if position.turn == 1 => {
+                        let caught_up = position
+                            .legal_moves()
+                            .iter()
+                            .any(|m| m.piece == Piece::King && m.to[1] == 0);
+                        (!caught_up).then_some(GameStatus::VariantWin(0))
+                    }
+                    (true, false) => Some(GameStatus::VariantWin(0)),
+                    (false, false) => None,
+                }
+            }
+            Variant::Atomic => {
+                (!position.has_king(position.turn)).then_some(GameStatus::VariantWin(mover))
+            }
+            // A side left without pieces has no moves either
+            Variant::Antichess => {
+                (!position.has_legal_moves()).then_some(GameStatus::VariantWin(position.turn))
+            }
+        }
+    }
+
+    /// Why the game ended when `status` returns a win.
+    pub fn win_reason(&self) -> &'static str {
+        match self {
+            Variant::Standard => "checkmate",
+            Variant::KingOfTheHill => "king reached the centre",
+            Variant::ThreeCheck => "third check",
+            Variant::RacingKings => "king reached the eighth rank",
+            Variant::Atomic => "king blown up",
+            Variant::Antichess => "no pieces or moves left",
+            Variant::Crazyhouse => "checkmate",
+        }
+    }
+
+    /// Why the game ended when `status` returns a draw.
+    pub fn draw_reason(&self) -> &'static str {
+        match self {
+            Variant::RacingKings => "both kings reached the eighth rank",
+            _ => "drawn by the variant's rules",
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    /// The name as written in the PGN `Variant` tag.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Variant::Standard => "Standard",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::ThreeCheck => "Three-check",
+            Variant::RacingKings => "Racing Kings",
+            Variant::Atomic => "Atomic",
+            Variant::Antichess => "Antichess",
+            Variant::Crazyhouse => "Crazyhouse",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    /// Reads a variant name in any case, ignoring spaces and dashes, so both `3check` and
+    /// the PGN tag `Three-check` are understood.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "chess" | "standard" => Ok(Variant::Standard),
+            "kingofthehill" | "koth" => Ok(Variant::KingOfTheHill),
+            "3check" | "threecheck" => Ok(Variant::ThreeCheck),
+            "racingkings" => Ok(Variant::RacingKings),
+            "atomic" => Ok(Variant::Atomic),
+            "antichess" | "losingchess" => Ok(Variant::Antichess),
+            "crazyhouse" => Ok(Variant::Crazyhouse),
+            _ => Err(format!(
+                "'{}' is not a variant, expected one of {}",
+                s,
+                Variant::ALL.map(|variant| variant.key()).join(", ")
+            )),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use chess_term::clock::{format_clock, time_for_move, Clock, Increment, TimeControl};
+use chess_term::pgn::parse_games;
+use chess_term::{GameRecord, GameResult, Position};
+
+fn secs(seconds: f64) -> Duration {
+    Duration::from_secs_f64(seconds)
+}
+
+fn control(text: &str, method: Increment) -> TimeControl {
+    TimeControl {
+        method,
+        ..text.parse().unwrap()
+    }
+}
+
+#[test]
+fn parses_time_controls() {
+    let control: TimeControl = "5+3".parse().unwrap();
+    assert_eq!(control.base, secs(300.0));
+    assert_eq!(control.increment, secs(3.0));
+    assert_eq!(control.method, Increment::Fischer);
+    assert_eq!(control.pgn_tag(), "300+3");
+
+    let control: TimeControl = "0.5".parse().unwrap();
+    assert_eq!(control.base, secs(30.0));
+    assert_eq!(control.increment, Duration::ZERO);
+    assert_eq!(control.pgn_tag(), "30");
+
+    for bad in [
+        "", "5+", "+3", "0+2", "-1", "5+-1", "five", "inf+1", "1e20", "5+1e30",
+    ] {
+        assert!(bad.parse::<TimeControl>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn increments_are_added_after_moves() {
+    let mut clock = Clock::new(control("1+2", Increment::Fischer));
+    assert!(clock.press(0, secs(5.0)));
+    assert_eq!(clock.remaining(0), secs(57.0));
+    assert_eq!(clock.remaining(1), secs(60.0));
+
+    let mut clock = Clock::new(control("1+2", Increment::Bronstein));
+    assert!(clock.press(0, secs(5.0)));
+    assert_eq!(clock.remaining(0), secs(57.0));
+    assert!(clock.press(0, secs(1.0)));
+    assert_eq!(clock.remaining(0), secs(57.0));
+}
+
+#[test]
+fn delay_passes_before_the_clock_runs() {
+    let mut clock = Clock::new(control("1+2", Increment::Delay));
+    assert_eq!(clock.running(0, secs(1.5)), secs(60.0));
+    assert_eq!(clock.running(0, secs(3.0)), secs(59.0));
+    assert!(clock.press(0, secs(1.0)));
+    assert_eq!(clock.remaining(0), secs(60.0));
+    assert!(clock.press(0, secs(5.0)));
+    assert_eq!(clock.remaining(0), secs(57.0));
+}
+
+#[test]
+fn flag_falls_when_time_runs_out() {
+    let mut clock = Clock::new(control("0.1+5", Increment::Fischer));
+    assert_eq!(clock.running(1, secs(10.0)), Duration::ZERO);
+    assert!(!clock.press(1, secs(6.0)));
+    assert_eq!(clock.remaining(1), Duration::ZERO);
+}
+
+#[test]
+fn formats_clocks() {
+    assert_eq!(format_clock(secs(9.87)), "9.8");
+    assert_eq!(format_clock(secs(10.0)), "0:10");
+    assert_eq!(format_clock(secs(299.9)), "4:59");
+    assert_eq!(format_clock(secs(3720.0)), "1:02:00");
+}
+
+#[test]
+fn budgets_time_for_a_move() {
+    assert_eq!(time_for_move(secs(60.0), secs(2.0), None), secs(3.5));
+    assert_eq!(
+        time_for_move(secs(10.0), Duration::ZERO, Some(5)),
+        secs(2.0)
+    );
+    // Never more than is left on the clock
+    assert_eq!(
+        time_for_move(secs(1.0), secs(10.0), Some(1)),
+        secs(1.0) - Duration::from_millis(50)
+    );
+    assert_eq!(
+        time_for_move(Duration::ZERO, Duration::ZERO, None),
+        Duration::from_millis(1)
+    );
+}
+
+#[test]
+fn mating_material() {
+    let cases = [
+        ("4k3/8/8/8/8/8/8/4K2R w - - 0 1", true, false),
+        ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/4KN1n w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", true, false),
+        ("4k3/8/8/8/8/8/8/3BKB2 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/6p1/2B1K3 w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/2B1Kb2 w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/2B1K1b1 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/4KNB1 w - - 0 1", true, false),
+    ];
+    for (fen, white, black) in cases {
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(position.has_mating_material(0), white, "{} white", fen);
+        assert_eq!(position.has_mating_material(1), black, "{} black", fen);
+    }
+}
+
+#[test]
+fn flag_fall_ends_the_game() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+    let mut record = GameRecord::new(start.clone());
+    record.flag_fell(1);
+    assert_eq!(record.result(), GameResult::WhiteWins);
+    assert_eq!(record.tag("Termination"), Some("time forfeit"));
+
+    let mut record = GameRecord::new(start);
+    record.flag_fell(0);
+    assert_eq!(record.result(), GameResult::Draw);
+
+    // Taking the last move back carries on the game
+    let mut record = GameRecord::new(Position::new());
+    record.push(Position::new().parse_uci_move("e2e4").unwrap());
+    record.flag_fell(1);
+    record.undo();
+    assert_eq!(record.result(), GameResult::Ongoing);
+    assert_eq!(record.tag("Termination"), None);
+}
+
+#[test]
+fn clock_times_round_trip_through_pgn() {
+    let mut record = GameRecord::new(Position::new());
+    for (uci, remaining) in [("e2e4", 298.0), ("e7e5", 295.0), ("g1f3", 3725.0)] {
+        record.push(record.position().parse_uci_move(uci).unwrap());
+        record.set_clock(secs(remaining));
+    }
+    record.push(record.position().parse_uci_move("b8c6").unwrap());
+
+    let pgn = record.to_pgn();
+    let movetext = pgn.split("\n\n").nth(1).unwrap().replace('\n', " ");
+    assert_eq!(
+        movetext.trim(),
+        "1. e4 {[%clk 0:04:58]} 1... e5 {[%clk 0:04:55]} 2. Nf3 {[%clk 1:02:05]} 2... Nc6 *"
+    );
+
+    let games = parse_games(&pgn).unwrap();
+    assert_eq!(
+        games[0].clocks(),
+        [
+            Some(secs(298.0)),
+            Some(secs(295.0)),
+            Some(secs(3725.0)),
+            None
+        ]
+    );
+}
+
+#[test]
+fn reads_fractions_of_seconds() {
+    let games = parse_games("1. e4 {[%clk 0:00:09.5]} e5 {Book [%clk 0:01:00]} *").unwrap();
+    assert_eq!(games[0].clocks(), [Some(secs(9.5)), Some(secs(60.0))]);
+
+    // A clock too large to hold is ignored like any other unreadable one
+    let games = parse_games("1. e4 {[%clk 99999999999999999999999]} *").unwrap();
+    assert_eq!(games[0].clocks(), [None]);
+}
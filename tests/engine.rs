@@ -0,0 +1,141 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chess_term::engine::{parse_engine_line, Engine, EngineError, EngineEvent, EngineInfo, Score};
+use chess_term::search::Limit;
+use chess_term::Position;
+
+/// Answers e2e4 from the starting position and e7e5 after 1. e4, and hangs on
+/// `go movetime 1`.
+const FAKE_ENGINE: &str = r#"#!/bin/sh
+best=e2e4
+while read -r line; do
+    case "$line" in
+        uci) echo "id name Fake Engine"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves e2e4") best=e7e5 ;;
+        position*) best=e2e4 ;;
+        "go movetime 1") ;;
+        "go infinite") echo "info depth 12 score cp 31 nodes 1000 pv $best" ;;
+        go*) echo "info depth 3 score cp 20 pv $best"; echo "bestmove $best" ;;
+        stop) echo "bestmove $best" ;;
+        quit) exit 0 ;;
+    esac
+done
+"#;
+
+/// Writes the fake engine once, before any test starts it, so no engine is spawned while
+/// the script is still open for writing.
+fn fake_engine() -> &'static str {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let path = std::env::temp_dir().join(format!("chess-term-engine-{}", std::process::id()));
+        fs::write(&path, FAKE_ENGINE).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    })
+    .to_str()
+    .unwrap()
+}
+
+#[test]
+fn parses_info_lines() {
+    assert_eq!(
+        parse_engine_line("info depth 20 seldepth 31 score cp -35 nodes 12 pv e2e4 e7e5 g1f3"),
+        Some(EngineEvent::Info(EngineInfo {
+            depth: Some(20),
+            score: Some(Score::Centipawns(-35)),
+            pv: vec!["e2e4".to_string(), "e7e5".to_string(), "g1f3".to_string()],
+        }))
+    );
+    assert_eq!(
+        parse_engine_line("info depth 5 score mate -2 upperbound pv h7h6"),
+        Some(EngineEvent::Info(EngineInfo {
+            depth: Some(5),
+            score: Some(Score::Mate(-2)),
+            pv: vec!["h7h6".to_string()],
+        }))
+    );
+    assert_eq!(parse_engine_line("info string depth 3 pv e2e4"), None);
+    assert_eq!(parse_engine_line("info nodes 100 nps 2000"), None);
+    assert_eq!(parse_engine_line("readyok"), None);
+    assert_eq!(parse_engine_line(""), None);
+}
+
+#[test]
+fn parses_best_move() {
+    assert_eq!(
+        parse_engine_line("bestmove e7e8q ponder d8e8"),
+        Some(EngineEvent::BestMove("e7e8q".to_string()))
+    );
+    assert_eq!(parse_engine_line("bestmove"), None);
+}
+
+#[test]
+fn plays_moves_for_the_given_position() {
+    let mut engine = Engine::start(fake_engine()).unwrap();
+    assert_eq!(engine.name.as_deref(), Some("Fake Engine"));
+    engine.new_game().unwrap();
+
+    let start = Position::new();
+    engine.set_position(&start, &[]).unwrap();
+    engine.go(Limit::Depth(3)).unwrap();
+    assert_eq!(engine.best_move().unwrap(), "e2e4");
+
+    let e4 = start.parse_uci_move("e2e4").unwrap();
+    engine.set_position(&start, &[e4]).unwrap();
+    engine.go(Limit::Time(Duration::from_millis(100))).unwrap();
+    assert_eq!(engine.best_move().unwrap(), "e7e5");
+}
+
+#[test]
+fn gives_up_on_an_engine_that_never_answers_go() {
+    let mut engine = Engine::start(fake_engine()).unwrap();
+    engine.set_position(&Position::new(), &[]).unwrap();
+    engine.go(Limit::Time(Duration::from_millis(1))).unwrap();
+    let started = Instant::now();
+    assert!(matches!(
+        engine.best_move(),
+        Err(EngineError::NoResponse(_))
+    ));
+    assert!(started.elapsed() < Duration::from_secs(10));
+    // The engine was told to stop, so its move arrives after all
+    assert_eq!(engine.stop().unwrap().as_deref(), Some("e2e4"));
+}
+
+#[test]
+fn analysis_runs_until_stopped() {
+    let mut engine = Engine::start(fake_engine()).unwrap();
+    engine.set_position(&Position::new(), &[]).unwrap();
+    engine.go(Limit::Infinite).unwrap();
+
+    let mut info = None;
+    for _ in 0..100 {
+        match engine.poll().unwrap() {
+            Some(EngineEvent::Info(i)) => {
+                info = Some(i);
+                break;
+            }
+            Some(EngineEvent::BestMove(m)) => panic!("analysis ended with {}", m),
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    let info = info.expect("no analysis from the engine");
+    assert_eq!(info.depth, Some(12));
+    assert_eq!(info.score, Some(Score::Centipawns(31)));
+    assert_eq!(info.pv, vec!["e2e4"]);
+
+    assert_eq!(engine.stop().unwrap(), Some("e2e4".to_string()));
+    // A stopped engine has nothing left to stop
+    assert_eq!(engine.stop().unwrap(), None);
+}
+
+#[test]
+fn missing_engine_is_an_error() {
+    let result = Engine::start("/nonexistent/chess-engine");
+    assert!(matches!(result, Err(EngineError::Io(_))));
+}
@@ -0,0 +1,198 @@
+use chess_term::{FenError, Piece, Position, Variant};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn error(fen: &str) -> FenError {
+    Position::from_fen(fen).unwrap_err()
+}
+
+#[test]
+fn round_trips() {
+    for fen in [
+        KIWIPETE,
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+    ] {
+        assert_eq!(Position::from_fen(fen).unwrap().to_fen(), fen);
+    }
+}
+
+#[test]
+fn accepts_epd() {
+    let position: Position = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -"
+        .parse()
+        .unwrap();
+    assert_eq!(position.to_fen(), KIWIPETE);
+}
+
+#[test]
+fn rejects_bad_structure() {
+    assert_eq!(error(""), FenError::WrongFieldCount(0));
+    assert_eq!(error("8/8/8 w - - 0"), FenError::WrongFieldCount(5));
+    assert_eq!(
+        error("8/8/8/8/8/8/8 w - - 0 1"),
+        FenError::WrongRankCount(7)
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K4 w - - 0 1"),
+        FenError::BadRankLength(1)
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K2 w - - 0 1"),
+        FenError::BadRankLength(1)
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4X3 w - - 0 1"),
+        FenError::BadPiece('X')
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+        FenError::BadSideToMove("x".to_string())
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+        FenError::BadHalfmoveClock("x".to_string())
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3 w - - 0 0"),
+        FenError::BadFullmoves("0".to_string())
+    );
+}
+
+#[test]
+fn rejects_impossible_positions() {
+    assert_eq!(
+        error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+        FenError::MissingKing(1)
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+        FenError::TooManyKings(0)
+    );
+    assert_eq!(
+        error("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"),
+        FenError::PawnOnBackRank
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+        FenError::OpponentInCheck
+    );
+}
+
+#[test]
+fn rejects_inconsistent_castling() {
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+        FenError::CastlingMismatch('K')
+    );
+    assert_eq!(
+        error("4k2r/8/8/8/8/8/8/4K3 w q - 0 1"),
+        FenError::CastlingMismatch('q')
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/R3K2R w KX - 0 1"),
+        FenError::BadCastling("KX".to_string())
+    );
+}
+
+#[test]
+fn rejects_impossible_en_passant() {
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3 w - e9 0 1"),
+        FenError::BadEnPassant("e9".to_string())
+    );
+    assert_eq!(
+        error("4k3/8/8/8/4P3/8/8/4K3 b - e4 0 1"),
+        FenError::ImpossibleEnPassant("e4".to_string())
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3 b - e3 0 1"),
+        FenError::ImpossibleEnPassant("e3".to_string())
+    );
+    assert!(Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").is_ok());
+}
+
+#[test]
+fn chess960_start_positions() {
+    let position = Position::chess960_start(518);
+    assert!(position.chess960);
+    assert_eq!(
+        position.to_fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    assert_eq!(
+        Position::chess960_start(0).to_shredder_fen(),
+        "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+    );
+    assert_eq!(
+        Position::chess960_start(959).to_fen(),
+        "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1"
+    );
+}
+
+#[test]
+fn reads_chess960_castling() {
+    let xfen = "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1";
+    let position = Position::from_fen(xfen).unwrap();
+    assert!(position.chess960);
+    assert_eq!(position.castling_rooks, [[7, 5], [7, 5]]);
+    assert_eq!(
+        Position::from_fen("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1").unwrap(),
+        position
+    );
+
+    // X-FEN names an inner rook by its file
+    let fen = "4k3/8/8/8/8/8/8/1R2K1RR w G - 0 1";
+    let position = Position::from_fen(fen).unwrap();
+    assert_eq!(position.castling_rooks[0][0], 6);
+    assert_eq!(position.to_fen(), fen);
+
+    assert!(!Position::from_fen(KIWIPETE).unwrap().chess960);
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/R3K3 w H - 0 1"),
+        FenError::CastlingMismatch('H')
+    );
+}
+
+#[test]
+fn reads_crazyhouse_pockets() {
+    let fen = "r1bqkbnr/ppp2ppp/2n5/4p3/4P3/5N2/PPP2PPP/RNBQKB1R[Pp] w KQkq - 0 5";
+    let position = Position::from_fen(fen).unwrap();
+    assert_eq!(position.variant, Variant::Crazyhouse);
+    assert_eq!(position.pocket(0, Piece::Pawn), 1);
+    assert_eq!(position.pocket(1, Piece::Pawn), 1);
+    assert_eq!(position.to_fen(), fen);
+
+    // The pocket may also be written as a ninth rank, and promoted pieces are marked
+    let position = Position::from_fen("4k3/1Q~6/8/8/8/8/8/4K3/NNq w - - 0 1").unwrap();
+    assert!(position.promoted[1][1]);
+    assert_eq!(position.pocket(0, Piece::Knight), 2);
+    assert_eq!(position.to_fen(), "4k3/1Q~6/8/8/8/8/8/4K3[NNq] w - - 0 1");
+
+    assert_eq!(
+        Variant::Crazyhouse.start().to_fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3[K] w - - 0 1"),
+        FenError::BadPocket("K".to_string())
+    );
+    assert_eq!(
+        error("4k3/8/8/8/8/8/8/4K3[Q w - - 0 1"),
+        FenError::BadPocket("Q".to_string())
+    );
+    assert_eq!(
+        Position::from_variant_fen("4k3/8/8/8/8/8/8/4K3[Q] w - - 0 1", Variant::Atomic),
+        Err(FenError::BadPocket("Q".to_string()))
+    );
+
+    // Promoted pieces only mean something when there are pockets
+    assert_eq!(
+        error("Q~7/8/8/8/8/8/8/k3K3 w - - 0 1"),
+        FenError::BadPiece('~')
+    );
+    assert_eq!(
+        Position::from_variant_fen("Q~7/8/8/8/8/8/8/k3K3 w - - 0 1", Variant::Atomic),
+        Err(FenError::BadPiece('~'))
+    );
+}
@@ -0,0 +1,80 @@
+use chess_term::layout::{Layout, SQUARE_SIZES};
+
+#[test]
+fn parses_square_sizes() {
+    assert_eq!("5x3".parse(), Ok(Layout::new((5, 3))));
+    assert_eq!("1X1".parse(), Ok(Layout::new((1, 1))));
+    for bad in ["", "5", "5x", "x3", "0x3", "5x0", "-1x2", "21x3", "5x3x1"] {
+        assert!(bad.parse::<Layout>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn fits_the_largest_squares() {
+    assert_eq!(Layout::fit(80, 24), Layout::new((3, 2)));
+    assert_eq!(Layout::fit(120, 50), Layout::new((9, 5)));
+    // Too small for anything still gets a board
+    assert_eq!(Layout::fit(20, 5), Layout::new((1, 1)));
+    for size in SQUARE_SIZES {
+        let layout = Layout::new(size);
+        assert_eq!(Layout::fit(layout.width(), layout.height()), layout);
+    }
+}
+
+#[test]
+fn the_original_layout_is_kept_for_single_cells() {
+    let layout = Layout::default();
+    assert_eq!(layout.square_centre(0, 0), (2, 1));
+    assert_eq!(layout.square_centre(7, 7), (9, 8));
+    assert_eq!(layout.label_row(), 9);
+    assert_eq!(layout.line_row(0), 10);
+    assert_eq!(layout.clock_column(), 12);
+    assert_eq!(layout.move_list_column(), 30);
+    assert_eq!(layout.move_list_rows(), 9);
+}
+
+#[test]
+fn every_cell_of_a_square_hits_it() {
+    for size in SQUARE_SIZES {
+        let layout = Layout::new(size);
+        for row in 0..8 {
+            for col in 0..8 {
+                let (left, top) = layout.square_origin(col, row);
+                for y in top..top + layout.square_height {
+                    for x in left..left + layout.square_width {
+                        assert_eq!(layout.square_at(x, y), Some((col, row)), "{:?}", size);
+                    }
+                }
+                let (x, y) = layout.square_centre(col, row);
+                assert_eq!(layout.square_at(x, y), Some((col, row)));
+            }
+        }
+        // The labels and everything right of and under the board miss it
+        assert_eq!(layout.square_at(1, 1), None);
+        assert_eq!(layout.square_at(2, 0), None);
+        assert_eq!(layout.square_at(2 + 8 * layout.square_width, 1), None);
+        assert_eq!(layout.square_at(2, layout.label_row()), None);
+    }
+}
+
+#[test]
+fn pockets_sit_inside_the_clocks() {
+    for size in SQUARE_SIZES {
+        let layout = Layout::new(size);
+        let (top, bottom) = layout.pocket_rows();
+        assert_eq!((top, bottom), (2, 8 * layout.square_height - 1));
+        for slot in 0..5 {
+            let x = layout.pocket_slot_column(slot);
+            assert!(x + 3 <= layout.move_list_column());
+            assert_eq!(layout.pocket_slot_at(x + 2, top, 5), Some((true, slot)));
+            assert_eq!(layout.pocket_slot_at(x, bottom, 5), Some((false, slot)));
+        }
+        let x = layout.pocket_slot_column(5);
+        assert_eq!(layout.pocket_slot_at(x, top, 5), None);
+        assert_eq!(layout.pocket_slot_at(layout.clock_column(), top, 5), None);
+        assert_eq!(
+            layout.pocket_slot_at(layout.pocket_slot_column(0), top + 1, 5),
+            None
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::thread;
+use std::time::Duration;
+
+use chess_term::network::{parse_message, Connection, Host, Message, NetError, NetEvent};
+use chess_term::{Position, Variant};
+
+/// Hosts a game from `start` on a free port and joins it from another thread.
+fn connect(start: &Position) -> (Connection, Connection, Position) {
+    let host = Host::listen(0).unwrap();
+    let addr = format!("127.0.0.1:{}", host.local_addr().unwrap().port());
+    let guest = thread::spawn(move || Connection::connect(&addr).unwrap());
+    let hosted = host.accept(start, 1).unwrap();
+    let (guest, position) = guest.join().unwrap();
+    (hosted, guest, position)
+}
+
+/// Waits for the next thing the other side does.
+fn next(connection: &mut Connection, position: &Position) -> Result<NetEvent, NetError> {
+    for _ in 0..500 {
+        if let Some(event) = connection.poll(position)? {
+            return Ok(event);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("nothing arrived");
+}
+
+#[test]
+fn messages_round_trip() {
+    let messages = [
+        Message::Hello(3),
+        Message::Game {
+            guest: 1,
+            variant: Variant::ThreeCheck,
+            fen: Variant::ThreeCheck.start().to_fen(),
+        },
+        Message::Move("e7e8q".to_string()),
+        Message::OfferDraw,
+        Message::AcceptDraw,
+        Message::DeclineDraw,
+        Message::ClaimDraw,
+        Message::Resign,
+    ];
+    for message in messages {
+        assert_eq!(parse_message(&message.to_string()), Some(message));
+    }
+    assert_eq!(parse_message("move"), None);
+    assert_eq!(
+        parse_message("game red chess 8/8/8/8/8/8/8/8 w - - 0 1"),
+        None
+    );
+    assert_eq!(parse_message("game white 8/8/8/8/8/8/8/8 w - - 0 1"), None);
+    assert_eq!(parse_message("hello"), None);
+}
+
+#[test]
+fn guest_starts_from_the_host_position() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    let (host, guest, position) = connect(&start);
+    assert_eq!(position, start);
+    assert_eq!(host.color, 0);
+    assert_eq!(guest.color, 1);
+}
+
+#[test]
+fn guest_plays_chess960_too() {
+    let start = Position::chess960_start(518);
+    let (_host, _guest, position) = connect(&start);
+    assert_eq!(position, start);
+}
+
+#[test]
+fn guest_plays_the_host_variant() {
+    let start = Variant::RacingKings.start();
+    let (_host, _guest, position) = connect(&start);
+    assert_eq!(position, start);
+    assert_eq!(position.variant, Variant::RacingKings);
+}
+
+#[test]
+fn moves_are_exchanged() {
+    let (mut host, mut guest, mut position) = connect(&Position::new());
+
+    let e4 = position.parse_uci_move("e2e4").unwrap();
+    host.send_move(&position, &e4).unwrap();
+    assert_eq!(next(&mut guest, &position), Ok(NetEvent::Move(e4)));
+    position.make_move(&e4);
+
+    let e5 = position.parse_uci_move("e7e5").unwrap();
+    guest.send_move(&position, &e5).unwrap();
+    assert_eq!(next(&mut host, &position), Ok(NetEvent::Move(e5)));
+}
+
+#[test]
+fn only_legal_moves_are_sent() {
+    let (mut host, mut guest, position) = connect(&Position::new());
+    let e4 = position.parse_uci_move("e2e4").unwrap();
+    // The guest plays Black, so cannot move for White
+    assert!(guest.send_move(&position, &e4).is_err());
+
+    let mut after = position.clone();
+    after.make_move(&e4);
+    assert!(host.send_move(&after, &e4).is_err());
+}
+
+#[test]
+fn illegal_moves_are_rejected() {
+    let (mut host, mut guest, position) = connect(&Position::new());
+    host.send(&Message::Move("e2e5".to_string())).unwrap();
+    assert!(matches!(
+        next(&mut guest, &position),
+        Err(NetError::Protocol(_))
+    ));
+
+    // Nor may the guest move out of turn
+    guest.send(&Message::Move("e7e5".to_string())).unwrap();
+    assert!(matches!(
+        next(&mut host, &position),
+        Err(NetError::Protocol(_))
+    ));
+}
+
+#[test]
+fn draw_offers_and_resignations_arrive() {
+    let (mut host, mut guest, position) = connect(&Position::new());
+    host.send(&Message::OfferDraw).unwrap();
+    assert_eq!(next(&mut guest, &position), Ok(NetEvent::OfferDraw));
+    guest.send(&Message::DeclineDraw).unwrap();
+    assert_eq!(next(&mut host, &position), Ok(NetEvent::DeclineDraw));
+    guest.send(&Message::AcceptDraw).unwrap();
+    assert_eq!(next(&mut host, &position), Ok(NetEvent::AcceptDraw));
+    host.send(&Message::Resign).unwrap();
+    assert_eq!(next(&mut guest, &position), Ok(NetEvent::Resign));
+}
+
+#[test]
+fn only_the_side_to_move_claims_draws() {
+    let (mut host, mut guest, position) = connect(&Position::new());
+    host.send(&Message::ClaimDraw).unwrap();
+    assert_eq!(next(&mut guest, &position), Ok(NetEvent::ClaimDraw));
+
+    // White is to move, so Black may not claim
+    guest.send(&Message::ClaimDraw).unwrap();
+    assert!(matches!(
+        next(&mut host, &position),
+        Err(NetError::Protocol(_))
+    ));
+}
+
+#[test]
+fn disconnection_is_noticed() {
+    let (host, mut guest, position) = connect(&Position::new());
+    drop(host);
+    assert_eq!(next(&mut guest, &position), Err(NetError::Disconnected));
+}
@@ -0,0 +1,236 @@
+use chess_term::perft::{divide, perft};
+use chess_term::{Position, Variant};
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn assert_perft(fen: &str, depth: usize, expected: u64) {
+    let position = Position::from_fen(fen).unwrap();
+    assert_eq!(
+        perft(&position, depth),
+        expected,
+        "perft({}) of {}",
+        depth,
+        fen
+    );
+}
+
+#[test]
+fn start_position() {
+    for (depth, expected) in [(1, 20), (2, 400), (3, 8902), (4, 197281)] {
+        assert_perft(START, depth, expected);
+    }
+}
+
+#[test]
+fn start_position_matches_default() {
+    assert_eq!(Position::from_fen(START).unwrap(), Position::new());
+}
+
+#[test]
+fn kiwipete() {
+    for (depth, expected) in [(1, 48), (2, 2039), (3, 97862)] {
+        assert_perft(KIWIPETE, depth, expected);
+    }
+}
+
+#[test]
+fn rook_and_pawn_endgame() {
+    let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    for (depth, expected) in [(1, 14), (2, 191), (3, 2812), (4, 43238), (5, 674624)] {
+        assert_perft(fen, depth, expected);
+    }
+}
+
+#[test]
+fn promotions_and_castling_out_of_check() {
+    let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    for (depth, expected) in [(1, 6), (2, 264), (3, 9467)] {
+        assert_perft(fen, depth, expected);
+    }
+}
+
+#[test]
+fn underpromotion_with_check() {
+    let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    for (depth, expected) in [(1, 44), (2, 1486), (3, 62379)] {
+        assert_perft(fen, depth, expected);
+    }
+}
+
+#[test]
+fn middlegame() {
+    let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+    for (depth, expected) in [(1, 46), (2, 2079), (3, 89890)] {
+        assert_perft(fen, depth, expected);
+    }
+}
+
+#[test]
+fn illegal_en_passant_pinned_on_rank() {
+    assert_perft("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1134888);
+}
+
+#[test]
+fn illegal_en_passant_pinned_on_diagonal() {
+    assert_perft("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1015133);
+}
+
+#[test]
+fn en_passant_capture_gives_check() {
+    assert_perft("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467);
+}
+
+#[test]
+fn short_castling_gives_check() {
+    assert_perft("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661072);
+}
+
+#[test]
+fn long_castling_gives_check() {
+    assert_perft("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803711);
+}
+
+#[test]
+fn castling_rights_lost_on_capture() {
+    assert_perft("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1274206);
+}
+
+#[test]
+fn castling_prevented_by_attacks() {
+    assert_perft("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1720476);
+}
+
+#[test]
+fn promote_out_of_check() {
+    assert_perft("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3821001);
+}
+
+#[test]
+fn discovered_check() {
+    assert_perft("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1004658);
+}
+
+#[test]
+fn promote_to_give_check() {
+    assert_perft("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217342);
+}
+
+#[test]
+fn underpromote_to_give_check() {
+    assert_perft("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92683);
+}
+
+#[test]
+fn self_stalemate() {
+    assert_perft("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217);
+}
+
+#[test]
+fn stalemate_and_checkmate() {
+    assert_perft("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567584);
+    assert_perft("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23527);
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let position = Position::from_fen(KIWIPETE).unwrap();
+    let split = divide(&position, 2);
+    assert_eq!(split.len(), 48);
+    assert_eq!(split.iter().map(|(_, n)| n).sum::<u64>(), 2039);
+}
+
+#[test]
+fn chess960_positions() {
+    for (fen, counts) in [
+        (
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            [21, 528, 12189, 326672],
+        ),
+        (
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            [21, 807, 18002, 667366],
+        ),
+        (
+            "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            [20, 479, 10471, 273318],
+        ),
+        (
+            "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+            [22, 593, 13440, 382958],
+        ),
+    ] {
+        for (depth, expected) in counts.into_iter().enumerate() {
+            assert_perft(fen, depth + 1, expected);
+        }
+    }
+}
+
+#[test]
+fn chess960_castling() {
+    let position = Position::from_fen("7k/8/8/8/8/8/8/RK4R1 w GA - 0 1").unwrap();
+    let castle = |uci: &str| {
+        let mut after = position.clone();
+        after.make_move(&position.parse_uci_move(uci).unwrap());
+        after.to_fen()
+    };
+    assert_eq!(castle("b1a1"), "7k/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+    assert_eq!(castle("b1g1"), "7k/8/8/8/8/8/8/R4RK1 b - - 1 1");
+
+    // The king may not cross d1, though the rook may
+    let position = Position::from_fen("3r3k/8/8/8/8/8/8/RK4R1 w GA - 0 1").unwrap();
+    let castles: Vec<String> = position
+        .legal_moves()
+        .iter()
+        .filter(|m| m.castle)
+        .map(|m| m.to_uci())
+        .collect();
+    assert_eq!(castles, ["b1a1"]);
+}
+
+#[test]
+fn atomic_positions() {
+    for (fen, counts) in [
+        (START, [20, 400, 8902, 197326]),
+        (
+            "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1",
+            [40, 1238, 45237, 1434825],
+        ),
+        (
+            "rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1",
+            [28, 833, 23353, 714499],
+        ),
+    ] {
+        let position = Position::from_variant_fen(fen, Variant::Atomic).unwrap();
+        for (depth, expected) in counts.into_iter().enumerate() {
+            assert_eq!(perft(&position, depth + 1), expected, "{}", fen);
+        }
+    }
+}
+
+#[test]
+fn antichess_start_position() {
+    let position = Variant::Antichess.start();
+    for (depth, expected) in [(1, 20), (2, 400), (3, 8067), (4, 153299)] {
+        assert_eq!(perft(&position, depth), expected);
+    }
+}
+
+#[test]
+fn crazyhouse_positions() {
+    for (fen, counts) in [
+        (
+            "2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1",
+            &[301, 75353][..],
+        ),
+        (
+            "4k3/1Q~6/8/8/4b3/8/Kpp5/8[] b - - 0 1",
+            &[20, 360, 5445, 132758],
+        ),
+    ] {
+        let position = Position::from_fen(fen).unwrap();
+        for (depth, &expected) in counts.iter().enumerate() {
+            assert_eq!(perft(&position, depth + 1), expected, "{}", fen);
+        }
+    }
+}
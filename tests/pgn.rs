@@ -0,0 +1,222 @@
+use chess_term::pgn::{parse_games, PgnError};
+use chess_term::{GameRecord, GameResult, Position, SanError};
+
+fn play(record: &mut GameRecord, moves: &[&str]) {
+    let mut position = record.position();
+    for uci in moves {
+        let m = position.parse_uci_move(uci).unwrap();
+        position.make_move(&m);
+        record.push(m);
+    }
+}
+
+fn san(fen: &str, uci: &str) -> String {
+    let position = Position::from_fen(fen).unwrap();
+    position.san(&position.parse_uci_move(uci).unwrap())
+}
+
+#[test]
+fn san_disambiguates() {
+    let fen = "1k6/8/8/8/8/8/8/R4RK1 w - - 0 1";
+    assert_eq!(san(fen, "a1c1"), "Rac1");
+    assert_eq!(san(fen, "f1f8"), "Rf8+");
+
+    let fen = "1k6/8/8/R7/8/8/8/R1K5 w - - 0 1";
+    assert_eq!(san(fen, "a1a3"), "R1a3");
+
+    let fen = "6k1/8/8/8/8/Q7/8/Q1Q4K w - - 0 1";
+    assert_eq!(san(fen, "a1b2"), "Qa1b2");
+}
+
+#[test]
+fn san_special_moves() {
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    assert_eq!(san(kiwipete, "e1g1"), "O-O");
+    assert_eq!(san(kiwipete, "e1c1"), "O-O-O");
+    assert_eq!(san(kiwipete, "d5e6"), "dxe6");
+    assert_eq!(san(kiwipete, "e5f7"), "Nxf7");
+
+    let fen = "8/8/8/2k5/3pP3/8/8/4K3 b - e3 0 1";
+    assert_eq!(san(fen, "d4e3"), "dxe3");
+
+    let fen = "1k6/5P2/8/8/8/8/8/K7 w - - 0 1";
+    assert_eq!(san(fen, "f7f8q"), "f8=Q+");
+    assert_eq!(san(fen, "f7f8n"), "f8=N");
+}
+
+#[test]
+fn exports_finished_game() {
+    let mut record = GameRecord::new(Position::new());
+    record.set_tag("White", "Fool");
+    play(&mut record, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+
+    assert_eq!(record.result(), GameResult::BlackWins);
+    assert_eq!(
+        record.to_pgn(),
+        "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+         [White \"Fool\"]\n[Black \"?\"]\n[Result \"0-1\"]\n\n\
+         1. f3 e5 2. g4 Qh4# 0-1\n"
+    );
+}
+
+#[test]
+fn exports_setup_position() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 30").unwrap();
+    let mut record = GameRecord::new(start);
+    play(&mut record, &["e8d7", "e2e4"]);
+
+    let pgn = record.to_pgn();
+    assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"]\n"));
+    assert!(pgn.ends_with("\n30... Kd7 31. e4 *\n"));
+}
+
+#[test]
+fn wraps_long_movetext() {
+    let mut record = GameRecord::new(Position::new());
+    for _ in 0..10 {
+        play(&mut record, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+    }
+
+    let pgn = record.to_pgn();
+    assert!(pgn.lines().all(|line| line.len() < 80));
+    assert_eq!(record.sans().len(), 40);
+}
+
+#[test]
+fn parses_san() {
+    let position = Position::new();
+    assert_eq!(position.parse_san("Nf3").unwrap().to_uci(), "g1f3");
+    assert_eq!(position.parse_san("e4!?").unwrap().to_uci(), "e2e4");
+    assert_eq!(position.parse_san("e2e4").unwrap().to_uci(), "e2e4");
+    assert_eq!(position.parse_san("Ng1-f3").unwrap().to_uci(), "g1f3");
+    assert_eq!(
+        position.parse_san("Ke2"),
+        Err(SanError::Illegal("Ke2".to_string()))
+    );
+    assert_eq!(
+        position.parse_san("hello"),
+        Err(SanError::Invalid("hello".to_string()))
+    );
+
+    let position = Position::from_fen("1k6/8/8/8/8/8/8/R4RK1 w - - 0 1").unwrap();
+    assert_eq!(
+        position.parse_san("Rc1"),
+        Err(SanError::Ambiguous("Rc1".to_string()))
+    );
+    assert_eq!(position.parse_san("Rfc1").unwrap().to_uci(), "f1c1");
+
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let position = Position::from_fen(kiwipete).unwrap();
+    assert_eq!(position.parse_san("O-O").unwrap().to_uci(), "e1g1");
+    assert_eq!(position.parse_san("0-0-0").unwrap().to_uci(), "e1c1");
+
+    let position = Position::from_fen("1k6/5P2/8/8/8/8/8/K7 w - - 0 1").unwrap();
+    assert_eq!(position.parse_san("f8=N").unwrap().to_uci(), "f7f8n");
+    assert_eq!(position.parse_san("f8Q+").unwrap().to_uci(), "f7f8q");
+}
+
+#[test]
+fn parses_typed_moves() {
+    let position = Position::new();
+    assert_eq!(position.parse_move("e2e4").unwrap().to_uci(), "e2e4");
+    assert_eq!(position.parse_move("Nc3").unwrap().to_uci(), "b1c3");
+    assert_eq!(
+        position.parse_move("e2e5"),
+        Err(SanError::Illegal("e2e5".to_string()))
+    );
+
+    let position = Position::from_fen("1k6/8/8/8/8/8/8/R4RK1 w - - 0 1").unwrap();
+    let uci: Vec<String> = position
+        .san_matches("Rc1")
+        .unwrap()
+        .iter()
+        .map(|m| m.to_uci())
+        .collect();
+    assert_eq!(uci, ["a1c1", "f1c1"]);
+}
+
+#[test]
+fn completes_moves() {
+    let position = Position::new();
+    assert_eq!(position.complete_move("N"), ["Na3", "Nc3", "Nf3", "Nh3"]);
+    assert_eq!(position.complete_move("e"), ["e3", "e4"]);
+    assert_eq!(position.complete_move("g1"), ["g1f3", "g1h3"]);
+    assert!(position.complete_move("Q").is_empty());
+}
+
+#[test]
+fn imports_games() {
+    let text = r#"[Event "First"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4 {gambit}) Nc6 $1 3. Bb5 a6;
+% escaped line with [brackets]
+4.Ba4 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 30"]
+
+30... Kd7 31. e4 *
+"#;
+    let games = parse_games(text).unwrap();
+    assert_eq!(games.len(), 2);
+
+    assert_eq!(games[0].tag("Event"), Some("First"));
+    assert_eq!(
+        games[0].sans(),
+        ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]
+    );
+    assert_eq!(games[0].result(), GameResult::WhiteWins);
+
+    assert_eq!(games[1].start().fullmoves, 30);
+    assert_eq!(games[1].sans(), ["Kd7", "e4"]);
+    assert_eq!(games[1].result(), GameResult::Ongoing);
+}
+
+#[test]
+fn import_round_trips_export() {
+    let mut record = GameRecord::new(Position::new());
+    play(
+        &mut record,
+        &[
+            "e2e4", "d7d5", "e4d5", "g8f6", "f1b5", "c7c6", "d5c6", "d8a5", "c6b7",
+        ],
+    );
+    let games = parse_games(&record.to_pgn()).unwrap();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].moves(), record.moves());
+    assert_eq!(games[0].to_pgn(), record.to_pgn());
+}
+
+#[test]
+fn chess960_games_round_trip() {
+    let mut record = GameRecord::new(Position::chess960_start(518));
+    play(
+        &mut record,
+        &["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1h1"],
+    );
+    let pgn = record.to_pgn();
+    assert!(pgn.contains("[Variant \"Chess960\"]\n"));
+    assert!(pgn.ends_with("4. O-O *\n"));
+
+    let games = parse_games(&pgn).unwrap();
+    assert!(games[0].start().chess960);
+    assert_eq!(games[0].moves(), record.moves());
+}
+
+#[test]
+fn reports_bad_moves() {
+    let error = parse_games("1. e4 e5 2. Ke3 *").unwrap_err();
+    assert_eq!(
+        error,
+        PgnError::BadMove {
+            game: 1,
+            ply: 3,
+            error: SanError::Illegal("Ke3".to_string())
+        }
+    );
+    assert!(parse_games("1. e4 { never closed").is_err());
+}
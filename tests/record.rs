@@ -0,0 +1,157 @@
+use chess_term::{DrawClaim, GameRecord, GameResult, GameStatus, Piece, Position};
+
+fn play(record: &mut GameRecord, moves: &[&str]) {
+    for uci in moves {
+        let m = record.position().parse_uci_move(uci).unwrap();
+        record.push(m);
+    }
+}
+
+#[test]
+fn undo_restores_every_field() {
+    let start = Position::from_fen("r3k2r/8/8/8/4p3/8/3P4/R3K2R w KQkq - 5 20").unwrap();
+    let mut record = GameRecord::new(start.clone());
+
+    play(&mut record, &["d2d4", "e4d3", "e1g1"]);
+    let after = record.position();
+    assert_eq!(after.castling_rights, [[false, false], [true, true]]);
+    assert_eq!(after.king_coords[0], [6, 7]);
+
+    assert_eq!(record.undo().unwrap().to_uci(), "e1g1");
+    let position = record.position();
+    assert_eq!(position.castling_rights, [[true, true], [true, true]]);
+    assert_eq!(position.king_coords[0], [4, 7]);
+
+    record.undo();
+    assert_eq!(record.position().en_passant, Some([3, 5]));
+    assert_eq!(record.position().halfmove_clock, 0);
+
+    record.undo();
+    assert_eq!(record.position(), start);
+    assert!(record.undo().is_none());
+
+    for _ in 0..3 {
+        record.redo();
+    }
+    assert_eq!(record.position(), after);
+    assert!(record.redo().is_none());
+}
+
+#[test]
+fn undo_promotion() {
+    let start = Position::from_fen("1k6/5P2/8/8/8/8/8/K7 w - - 0 1").unwrap();
+    let mut record = GameRecord::new(start.clone());
+    play(&mut record, &["f7f8n"]);
+    assert_eq!(record.position().square(5, 0).piece, Piece::Knight);
+
+    record.undo();
+    assert_eq!(record.position(), start);
+    assert_eq!(record.position().square(5, 1).piece, Piece::Pawn);
+}
+
+#[test]
+fn new_move_discards_redo() {
+    let mut record = GameRecord::new(Position::new());
+    play(&mut record, &["e2e4", "e7e5"]);
+    record.undo();
+    play(&mut record, &["c7c5"]);
+    assert!(record.redo().is_none());
+    assert_eq!(record.sans(), ["e4", "c5"]);
+
+    record.truncate(1);
+    assert_eq!(record.moves().len(), 1);
+    assert_eq!(record.position(), record.position_at(1));
+}
+
+#[test]
+fn detects_repetitions() {
+    let mut record = GameRecord::new(Position::new());
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+    play(&mut record, &shuffle);
+    assert_eq!(record.repetitions(), 2);
+    assert_eq!(record.claimable_draw(), None);
+
+    play(&mut record, &shuffle);
+    assert_eq!(record.repetitions(), 3);
+    assert_eq!(
+        record.claimable_draw(),
+        Some(DrawClaim::ThreefoldRepetition)
+    );
+    assert_eq!(record.result(), GameResult::Ongoing);
+
+    play(&mut record, &shuffle);
+    play(&mut record, &shuffle);
+    assert_eq!(record.status(), GameStatus::FivefoldRepetition);
+    assert_eq!(record.result(), GameResult::Draw);
+}
+
+#[test]
+fn castling_rights_break_repetition() {
+    let start = Position::from_fen("r3k3/8/8/8/8/8/8/4K3 b q - 0 1").unwrap();
+    let mut record = GameRecord::new(start);
+    play(&mut record, &["a8a7", "e1e2", "a7a8", "e2e1"]);
+    assert_eq!(record.repetitions(), 1);
+}
+
+#[test]
+fn unusable_en_passant_square_is_ignored() {
+    let with_square = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+    let without = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+    assert_eq!(with_square.repetition_key(), without.repetition_key());
+
+    let with_square = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+    let without = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+    assert_ne!(with_square.repetition_key(), without.repetition_key());
+}
+
+#[test]
+fn move_count_rules() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+    let mut record = GameRecord::new(start);
+    assert_eq!(record.claimable_draw(), None);
+    play(&mut record, &["a1a2"]);
+    assert_eq!(record.claimable_draw(), Some(DrawClaim::FiftyMoveRule));
+
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 150 120").unwrap();
+    assert_eq!(position.status(), GameStatus::SeventyFiveMoveRule);
+
+    let mated = Position::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 120").unwrap();
+    assert_eq!(mated.status(), GameStatus::Checkmate);
+}
+
+#[test]
+fn insufficient_material() {
+    for fen in [
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4KN2 b - - 0 1",
+        "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "2b1k3/8/8/8/8/8/8/3BKB2 w - - 0 1",
+    ] {
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(
+            position.status(),
+            GameStatus::InsufficientMaterial,
+            "{}",
+            fen
+        );
+    }
+
+    for fen in [
+        "4k3/8/8/8/8/8/8/4KBB1 w - - 0 1",
+        "4kb2/8/8/8/8/8/8/3BK3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4KNN1 w - - 0 1",
+        "4kn2/8/8/8/8/8/8/4KB2 w - - 0 1",
+        "4k3/8/8/8/8/8/7P/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+    ] {
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(position.status(), GameStatus::Ongoing, "{}", fen);
+    }
+
+    let mut record =
+        GameRecord::new(Position::from_fen("4k3/8/8/8/8/8/3q4/4KB2 w - - 0 1").unwrap());
+    play(&mut record, &["e1d2"]);
+    assert_eq!(record.result(), GameResult::Draw);
+}
@@ -0,0 +1,109 @@
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use chess_term::search::{evaluate, search, search_with, Limit};
+use chess_term::{Position, Variant};
+
+fn best_move(fen: &str, depth: usize) -> String {
+    let position = Position::from_fen(fen).unwrap();
+    search(&position, Limit::Depth(depth))
+        .best_move()
+        .unwrap()
+        .to_uci()
+}
+
+#[test]
+fn start_position_is_balanced() {
+    assert_eq!(evaluate(&Position::new()), 0);
+}
+
+#[test]
+fn finds_mate_in_one() {
+    // Back rank mate
+    let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let info = search(&position, Limit::Depth(3));
+    assert_eq!(info.best_move().unwrap().to_uci(), "a1a8");
+    assert_eq!(info.mate_in(), Some(1));
+}
+
+#[test]
+fn finds_mate_in_two() {
+    let position = Position::from_fen("k7/8/2K5/8/8/8/8/5R2 w - - 0 1").unwrap();
+    let info = search(&position, Limit::Depth(4));
+    assert_eq!(info.mate_in(), Some(2));
+}
+
+#[test]
+fn sees_being_mated() {
+    let position = Position::from_fen("7k/8/8/8/8/8/5PPP/r6K w - - 0 1").unwrap();
+    let info = search(&position, Limit::Depth(2));
+    assert_eq!(info.mate_in(), Some(0));
+    assert!(info.best_move().is_none());
+
+    // Making room for the king avoids the back rank mate
+    let position = Position::from_fen("r5k1/8/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+    let info = search(&position, Limit::Depth(3));
+    assert_eq!(info.mate_in(), None);
+}
+
+#[test]
+fn sees_explosions_past_the_horizon() {
+    // Every move lets Qxg2 blow up the king, which only quiescence gets to see
+    let position =
+        Position::from_variant_fen("k7/1q6/8/8/8/8/6PP/7K w - - 0 1", Variant::Atomic).unwrap();
+    let info = search(&position, Limit::Depth(1));
+    assert_eq!(info.mate_in(), Some(-1));
+}
+
+#[test]
+fn takes_a_hanging_queen() {
+    assert_eq!(best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2), "d2d5");
+}
+
+#[test]
+fn does_not_take_a_defended_pawn_with_the_queen() {
+    let m = best_move("4k3/2p5/3p4/8/8/8/3Q4/4K3 w - - 0 1", 3);
+    assert_ne!(m, "d2d6");
+}
+
+#[test]
+fn stalemate_has_no_move() {
+    let position = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    let info = search(&position, Limit::Depth(3));
+    assert_eq!(info.score, 0);
+    assert!(info.best_move().is_none());
+}
+
+#[test]
+fn reports_every_depth() {
+    let mut depths = Vec::new();
+    let info = search_with(
+        &Position::new(),
+        Limit::Depth(3),
+        &AtomicBool::new(false),
+        |info| depths.push(info.depth),
+    );
+    assert_eq!(depths, vec![1, 2, 3]);
+    assert_eq!(info.depth, 3);
+    assert!(info.best_move().is_some());
+}
+
+#[test]
+fn time_limit_is_respected() {
+    let started = Instant::now();
+    let info = search(&Position::new(), Limit::Time(Duration::from_millis(200)));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(info.best_move().is_some());
+}
+
+#[test]
+fn stop_flag_still_returns_a_move() {
+    let info = search_with(
+        &Position::new(),
+        Limit::Depth(30),
+        &AtomicBool::new(true),
+        |_| (),
+    );
+    assert_eq!(info.depth, 1);
+    assert!(info.best_move().is_some());
+}
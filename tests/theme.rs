@@ -0,0 +1,149 @@
+use std::fs;
+
+use chess_term::theme::{
+    builtin_theme, Config, ConfigError, Palette, PieceSet, Rgb, Theme, BUILTIN_THEMES,
+};
+
+#[test]
+fn parses_colours() {
+    assert_eq!("#c8641E".parse(), Ok(Rgb(200, 100, 30)));
+    for bad in ["c8641e", "#c8641", "#c8641e0", "#g8641e", "#ééé"] {
+        assert!(bad.parse::<Rgb>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn builtin_themes_exist() {
+    for name in BUILTIN_THEMES {
+        assert!(builtin_theme(name).is_some(), "{}", name);
+    }
+    assert_eq!(Config::default().theme(None), Ok(Theme::default()));
+    assert_eq!(
+        Config::default().theme(Some("wood")),
+        Ok(builtin_theme("wood").unwrap())
+    );
+}
+
+#[test]
+fn themes_from_the_config_file() {
+    let config: Config = r##"
+        theme = "mine"
+
+        [themes.mine]
+        light_square = "#eeeed2"
+        dark_square = "#769656"
+
+        [themes.wood]
+        check = "#ff0000"
+    "##
+    .parse()
+    .unwrap();
+
+    // Colours left out come from the default theme
+    let mine = config.theme(None).unwrap();
+    assert_eq!(mine.light_square, Rgb(238, 238, 210));
+    assert_eq!(mine.dark_square, Rgb(118, 150, 86));
+    assert_eq!(mine.highlight, Theme::default().highlight);
+
+    // The command line wins over the file, and the file over built-in themes
+    assert_eq!(config.theme(Some("wood")).unwrap().check, Rgb(255, 0, 0));
+    assert_eq!(
+        config.theme(Some("ocean")),
+        Ok(builtin_theme("ocean").unwrap())
+    );
+    assert_eq!(
+        config.theme(Some("neon")),
+        Err(ConfigError::UnknownTheme("neon".to_string()))
+    );
+}
+
+#[test]
+fn bad_config_files_are_errors() {
+    for bad in [
+        "[themes.mine]\nlight_square = \"white\"",
+        "[themes.mine]\nsquares = \"#ffffff\"",
+        "theme = ",
+    ] {
+        assert!(
+            matches!(bad.parse::<Config>(), Err(ConfigError::Parse(_))),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn missing_config_file() {
+    let path = std::env::temp_dir().join(format!("chess-term-config-{}", std::process::id()));
+    assert_eq!(Config::load(&path, false), Ok(Config::default()));
+    assert!(matches!(Config::load(&path, true), Err(ConfigError::Io(_))));
+
+    fs::write(&path, "theme = \"mono\"").unwrap();
+    let config = Config::load(&path, true).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(config.theme(None), Ok(builtin_theme("mono").unwrap()));
+}
+
+#[test]
+fn colours_in_smaller_palettes() {
+    assert_eq!(Rgb(0, 0, 0).ansi256(), 16);
+    assert_eq!(Rgb(255, 255, 255).ansi256(), 231);
+    assert_eq!(Rgb(128, 128, 128).ansi256(), 243);
+    assert_eq!(Rgb(255, 0, 0).ansi256(), 196);
+    assert_eq!(Rgb(181, 136, 99).ansi256(), 137);
+
+    assert_eq!(Rgb(200, 10, 10).ansi16(), 1);
+    assert_eq!(Rgb(85, 255, 85).ansi16(), 10);
+    assert_eq!(Rgb(200, 200, 200).ansi16(), 7);
+    assert_eq!(Rgb(20, 20, 20).ansi16(), 0);
+
+    let colour = Rgb(85, 255, 85);
+    assert_eq!(Palette::TrueColor.bg(colour), "\x1b[48;2;85;255;85m");
+    assert_eq!(Palette::Ansi256.bg(colour), "\x1b[48;5;83m");
+    assert_eq!(Palette::Ansi16.bg(colour), "\x1b[102m");
+    assert_eq!(Palette::Ansi16.fg(Rgb(0, 0, 0)), "\x1b[30m");
+    assert_eq!(Palette::NoColor.bg(colour), "");
+}
+
+fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |name| {
+        vars.iter()
+            .find(|(var, _)| *var == name)
+            .map(|(_, value)| value.to_string())
+    }
+}
+
+#[test]
+fn detects_the_palette() {
+    let cases = [
+        (
+            &[("TERM", "xterm-256color"), ("NO_COLOR", "1")][..],
+            Palette::NoColor,
+        ),
+        (&[("TERM", "xterm"), ("NO_COLOR", "")], Palette::Ansi16),
+        (
+            &[("TERM", "xterm"), ("COLORTERM", "truecolor")],
+            Palette::TrueColor,
+        ),
+        (&[("TERM", "screen-256color")], Palette::Ansi256),
+        (&[("TERM", "dumb")], Palette::NoColor),
+        (&[], Palette::Ansi16),
+    ];
+    for (vars, palette) in cases {
+        assert_eq!(Palette::detect_with(env(vars)), palette, "{:?}", vars);
+    }
+}
+
+#[test]
+fn detects_the_piece_set() {
+    let cases = [
+        (&[("LANG", "en_GB.UTF-8")][..], PieceSet::Unicode),
+        (&[("LC_ALL", "C"), ("LANG", "en_GB.UTF-8")], PieceSet::Ascii),
+        (&[("LC_ALL", ""), ("LC_CTYPE", "C.utf8")], PieceSet::Unicode),
+        (&[("LANG", "C")], PieceSet::Ascii),
+        (&[], PieceSet::Ascii),
+    ];
+    for (vars, pieces) in cases {
+        assert_eq!(PieceSet::detect_with(env(vars)), pieces, "{:?}", vars);
+    }
+}
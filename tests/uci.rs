@@ -0,0 +1,181 @@
+use std::io::{BufReader, Cursor, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chess_term::Position;
+
+/// Output the UCI loop writes to, readable after it has finished.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Input that gives its commands and then waits forever, like a GUI that says no more.
+struct OpenInput(Cursor<String>);
+
+impl Read for OpenInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.0.read(buf)?;
+        if n == 0 {
+            loop {
+                thread::park();
+            }
+        }
+        Ok(n)
+    }
+}
+
+fn run(commands: &str) -> Vec<String> {
+    let output = Output::default();
+    chess_term::uci::run(Cursor::new(commands.to_string()), output.clone());
+    let bytes = output.0.lock().unwrap().clone();
+    String::from_utf8(bytes)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn best_moves(lines: &[String]) -> Vec<&str> {
+    lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("bestmove "))
+        .collect()
+}
+
+#[test]
+fn handshake() {
+    let lines = run("uci\nisready\nquit\n");
+    assert!(lines[0].starts_with("id name chess-term"));
+    assert!(lines[1].starts_with("id author"));
+    assert_eq!(
+        lines[2..],
+        [
+            "option name UCI_Chess960 type check default false",
+            "option name UCI_Variant type combo default chess var chess var kingofthehill \
+             var 3check var racingkings var atomic var antichess var crazyhouse",
+            "uciok",
+            "readyok"
+        ]
+    );
+}
+
+#[test]
+fn chess960_castles_by_taking_the_rook() {
+    let moves = "position startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 e1h1\n";
+    let lines = run(moves);
+    assert_eq!(lines, ["info string e1h1 is not a legal move"]);
+
+    let lines = run(&format!(
+        "setoption name UCI_Chess960 value true\n{}go depth 1\n",
+        moves
+    ));
+    assert_eq!(best_moves(&lines).len(), 1);
+    assert!(lines.iter().all(|l| !l.starts_with("info string")));
+}
+
+#[test]
+fn plays_the_chosen_variant() {
+    let position = "position fen 7k/8/8/8/8/5K2/8/8 w - - 0 1\ngo depth 1\n";
+    let lines = run(&format!(
+        "setoption name UCI_Variant value kingofthehill\n{}",
+        position
+    ));
+    assert!(lines[0].starts_with("info depth 1 score mate 1 "));
+    assert_eq!(best_moves(&lines), ["f3e4"]);
+
+    let lines = run("setoption name UCI_Variant value atomicbomb\n");
+    assert!(lines[0].starts_with("info string 'atomicbomb' is not a variant"));
+}
+
+#[test]
+fn searches_to_a_depth() {
+    let lines = run("position startpos moves e2e4 e7e5\ngo depth 2\n");
+    let infos: Vec<&String> = lines.iter().filter(|l| l.starts_with("info")).collect();
+    assert_eq!(infos.len(), 2);
+    assert!(infos[0].starts_with("info depth 1 score cp "));
+    assert!(infos[1].starts_with("info depth 2 score cp "));
+    assert!(infos[1].contains(" pv "));
+    assert_eq!(best_moves(&lines).len(), 1);
+}
+
+#[test]
+fn finds_mate_from_a_fen() {
+    let lines = run("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo movetime 100\n");
+    assert!(lines.iter().any(|l| l.contains("score mate 1")));
+    assert_eq!(best_moves(&lines), ["a1a8"]);
+}
+
+#[test]
+fn plays_the_side_to_move_on_the_clock() {
+    let lines = run("position startpos moves e2e4\ngo wtime 1000 btime 1000 winc 10 binc 10\n");
+    let mut position = Position::new();
+    position.make_move(&position.parse_uci_move("e2e4").unwrap());
+    assert!(position.parse_uci_move(best_moves(&lines)[0]).is_some());
+
+    let lines = run("position fen 6k1/5ppp/8/8/8/8/8/R6K b - - 0 1\n\
+         go wtime 100 btime 2000 movestogo 10\n");
+    assert_eq!(best_moves(&lines).len(), 1);
+    assert_ne!(best_moves(&lines)[0], "0000");
+}
+
+#[test]
+fn infinite_search_waits_for_stop() {
+    let lines = run("position startpos\ngo infinite\nisready\nstop\nquit\n");
+    assert!(lines.contains(&"readyok".to_string()));
+    assert_eq!(best_moves(&lines).len(), 1);
+    assert_eq!(
+        lines.last().unwrap(),
+        lines.iter().rfind(|l| l.starts_with("bestmove")).unwrap()
+    );
+}
+
+#[test]
+fn node_and_mate_limits_end_without_stop() {
+    for go in ["go nodes 1000", "go mate 1"] {
+        let output = Output::default();
+        let input = OpenInput(Cursor::new(format!("position startpos\n{}\n", go)));
+        let writer = output.clone();
+        thread::spawn(move || chess_term::uci::run(BufReader::new(input), writer));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !String::from_utf8_lossy(&output.0.lock().unwrap()).contains("bestmove ") {
+            assert!(Instant::now() < deadline, "{} never sent bestmove", go);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+#[test]
+fn go_waits_for_the_previous_search() {
+    let lines = run("position startpos\ngo depth 2\ngo depth 1\n");
+    assert_eq!(best_moves(&lines).len(), 2);
+}
+
+#[test]
+fn bad_positions_are_reported() {
+    let lines = run("position startpos moves e2e5\nposition fen 8/8/8 w - - 0 1\nposition\n");
+    assert_eq!(
+        lines,
+        [
+            "info string e2e5 is not a legal move",
+            "info string expected 8 ranks, found 3",
+            "info string cannot set up a position from ''",
+        ]
+    );
+}
+
+#[test]
+fn no_legal_moves_gives_null_move() {
+    let lines = run("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo depth 3\n");
+    assert_eq!(best_moves(&lines), ["0000"]);
+}
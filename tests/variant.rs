@@ -0,0 +1,304 @@
+use chess_term::pgn::parse_games;
+use chess_term::{FenError, GameRecord, GameResult, GameStatus, Piece, Position, Variant};
+
+fn play(position: &mut Position, moves: &[&str]) {
+    for uci in moves {
+        let m = position.parse_uci_move(uci).unwrap();
+        position.make_move(&m);
+    }
+}
+
+fn variant_fen(fen: &str, variant: Variant) -> Position {
+    Position::from_variant_fen(fen, variant).unwrap()
+}
+
+#[test]
+fn parses_names() {
+    for variant in Variant::ALL {
+        assert_eq!(variant.key().parse(), Ok(variant));
+        assert_eq!(variant.to_string().parse(), Ok(variant));
+    }
+    assert_eq!("King of the Hill".parse(), Ok(Variant::KingOfTheHill));
+    assert!("atomicbomb".parse::<Variant>().is_err());
+}
+
+#[test]
+fn king_of_the_hill() {
+    let mut position = variant_fen("7k/8/8/8/8/5K2/8/r7 w - - 0 1", Variant::KingOfTheHill);
+    assert_eq!(position.status(), GameStatus::Ongoing);
+    play(&mut position, &["f3e4"]);
+    assert_eq!(position.status(), GameStatus::VariantWin(0));
+
+    // A lone king can still win, so bare kings are no draw
+    let position = variant_fen("7k/8/8/8/8/8/8/K7 w - - 0 1", Variant::KingOfTheHill);
+    assert_eq!(position.status(), GameStatus::Ongoing);
+}
+
+#[test]
+fn three_check_counts_checks() {
+    let mut position = Variant::ThreeCheck.start();
+    assert_eq!(
+        position.to_fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1"
+    );
+
+    play(
+        &mut position,
+        &["e2e4", "f7f6", "d1h5", "g7g6", "h5g6", "h7g6"],
+    );
+    assert_eq!(position.checks, [2, 0]);
+    assert!(position.to_fen().contains(" 1+3 "));
+    assert_eq!(position.status(), GameStatus::Ongoing);
+
+    play(&mut position, &["f1e2", "a7a6", "e2h5", "a6a5", "h5g6"]);
+    assert_eq!(position.checks, [3, 0]);
+    assert_eq!(position.status(), GameStatus::VariantWin(0));
+}
+
+#[test]
+fn three_check_fen() {
+    let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 2+3 0 2";
+    let position = Position::from_fen(fen).unwrap();
+    assert_eq!(position.variant, Variant::ThreeCheck);
+    assert_eq!(position.checks, [1, 0]);
+    assert_eq!(position.to_fen(), fen);
+
+    // Lichess writes the checks given after the move counters
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 +2+0").unwrap();
+    assert_eq!(position.checks, [2, 0]);
+    assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 1+3 0 1");
+
+    assert!(matches!(
+        Position::from_variant_fen("4k3/8/8/8/8/8/8/4K3 w - - 3+x 0 1", Variant::ThreeCheck),
+        Err(FenError::BadChecks(_))
+    ));
+    assert!(matches!(
+        Position::from_variant_fen("4k3/8/8/8/8/8/8/4K3 w - - 3+3 0 1", Variant::KingOfTheHill),
+        Err(FenError::BadChecks(_))
+    ));
+}
+
+#[test]
+fn three_check_minor_pieces_can_win() {
+    // A lone minor piece can still give three checks, so only bare kings are drawn
+    for fen in [
+        "4k3/8/8/8/8/8/8/4KB2 w - - 3+3 0 1",
+        "4k3/8/8/8/8/8/8/4KN2 w - - 3+3 0 1",
+    ] {
+        let position = variant_fen(fen, Variant::ThreeCheck);
+        assert_eq!(position.status(), GameStatus::Ongoing, "{}", fen);
+        assert!(position.has_mating_material(0), "{}", fen);
+        assert!(!position.has_mating_material(1), "{}", fen);
+    }
+
+    let position = variant_fen("4k3/8/8/8/8/8/8/4K3 w - - 3+3 0 1", Variant::ThreeCheck);
+    assert_eq!(position.status(), GameStatus::InsufficientMaterial);
+}
+
+#[test]
+fn racing_kings_forbids_checks() {
+    let position = Variant::RacingKings.start();
+    assert_eq!(position.to_fen(), "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1");
+    assert_eq!(position.legal_moves().len(), 21);
+
+    // Rh1 and Rg8 would both check the black king
+    let position = variant_fen("7k/8/8/8/8/8/8/K5R1 w - - 0 1", Variant::RacingKings);
+    let moves: Vec<String> = position.legal_moves().iter().map(|m| m.to_uci()).collect();
+    assert!(moves.contains(&"g1g7".to_string()));
+    assert!(!moves.contains(&"g1h1".to_string()));
+    assert!(!moves.contains(&"g1g8".to_string()));
+}
+
+#[test]
+fn racing_kings_finish() {
+    // White gets there first and Black cannot follow
+    let mut position = variant_fen("8/1K6/8/8/8/8/8/7k w - - 0 1", Variant::RacingKings);
+    play(&mut position, &["b7b8"]);
+    assert_eq!(position.status(), GameStatus::VariantWin(0));
+
+    // Black can follow, so the game goes on for one more move and ends drawn
+    let mut position = variant_fen("8/1K4k1/8/8/8/8/8/8 w - - 0 1", Variant::RacingKings);
+    play(&mut position, &["b7b8"]);
+    assert_eq!(position.status(), GameStatus::Ongoing);
+    play(&mut position, &["g7g8"]);
+    assert_eq!(position.status(), GameStatus::VariantDraw);
+}
+
+#[test]
+fn variant_games_round_trip() {
+    let mut record = GameRecord::new(Variant::KingOfTheHill.start());
+    for uci in ["e2e3", "e7e6", "e1e2", "e8e7", "e2d3", "e7d6", "d3e4"] {
+        let m = record.position().parse_uci_move(uci).unwrap();
+        record.push(m);
+    }
+    assert_eq!(record.result(), GameResult::WhiteWins);
+
+    let pgn = record.to_pgn();
+    assert!(pgn.contains("[Variant \"King of the Hill\"]\n"));
+    assert!(!pgn.contains("[FEN "));
+
+    let games = parse_games(&pgn).unwrap();
+    assert_eq!(games[0].start().variant, Variant::KingOfTheHill);
+    assert_eq!(games[0].moves(), record.moves());
+    assert_eq!(games[0].result(), GameResult::WhiteWins);
+}
+
+#[test]
+fn atomic_captures_explode() {
+    let mut position = variant_fen(
+        "r1bqk1nr/pppp1ppp/2nb4/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        Variant::Atomic,
+    );
+    play(&mut position, &["f3e5"]);
+    // Knight, pawn and the bishop beside them go, the pawn on e4 stays
+    assert_eq!(
+        position.to_fen(),
+        "r1bqk1nr/pppp1ppp/2n5/8/4P3/8/PPPP1PPP/RNBQKB1R b KQkq - 0 3"
+    );
+
+    // Taking on f7 blows up the king on e8 and wins
+    let mut position = variant_fen(
+        "rnbqkbnr/pppp1ppp/8/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 0 2",
+        Variant::Atomic,
+    );
+    let m = position.parse_uci_move("h5e5").unwrap();
+    assert_eq!(position.san(&m), "Qxe5");
+    let m = position.parse_uci_move("h5f7").unwrap();
+    assert_eq!(position.san(&m), "Qxf7#");
+    position.make_move(&m);
+    assert!(!position.has_king(1));
+    assert_eq!(position.status(), GameStatus::VariantWin(0));
+}
+
+#[test]
+fn atomic_kings() {
+    // Kings cannot capture, and one next to the other cannot be checked
+    let position = variant_fen("8/8/8/8/8/3kq3/4K3/8 w - - 0 1", Variant::Atomic);
+    assert!(!position.in_check());
+    let moves: Vec<String> = position.legal_moves().iter().map(|m| m.to_uci()).collect();
+    assert!(!moves.contains(&"e2e3".to_string()));
+    assert!(!moves.contains(&"e2d3".to_string()));
+
+    // A capture next to one's own king is not allowed
+    let position = variant_fen("4k3/8/8/8/8/8/3pP3/3RK3 w - - 0 1", Variant::Atomic);
+    assert!(position.parse_uci_move("d1d2").is_none());
+}
+
+#[test]
+fn antichess_captures_are_compulsory() {
+    let mut position = Variant::Antichess.start();
+    assert_eq!(position.castling_rights, [[false, false], [false, false]]);
+    play(&mut position, &["e2e4", "d7d5"]);
+    let moves: Vec<String> = position.legal_moves().iter().map(|m| m.to_uci()).collect();
+    assert_eq!(moves, ["e4d5"]);
+    assert!(position.parse_uci_move("g1f3").is_none());
+    assert!(position.moves_from(6, 7).is_empty());
+}
+
+#[test]
+fn antichess_is_won_by_losing_everything() {
+    // The king can be taken, and the side left with nothing wins
+    let mut position = variant_fen("8/8/8/8/8/8/1k6/K7 w - - 0 1", Variant::Antichess);
+    assert!(!position.in_check());
+    play(&mut position, &["a1b2"]);
+    assert_eq!(position.status(), GameStatus::VariantWin(1));
+
+    // Pawns may promote to kings
+    let position = variant_fen("8/P7/8/8/8/8/8/7k w - - 0 1", Variant::Antichess);
+    assert_eq!(position.legal_moves().len(), 5);
+    assert!(position.parse_uci_move("a7a8k").is_some());
+
+    // Bare kings are no draw
+    let position = variant_fen("8/8/8/3k4/8/8/8/K7 w - - 0 1", Variant::Antichess);
+    assert_eq!(position.status(), GameStatus::Ongoing);
+}
+
+#[test]
+fn variants_without_castling_reject_castling_rights() {
+    let fen = Variant::Antichess.start().to_fen();
+    assert_eq!(fen, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
+    assert_eq!(variant_fen(&fen, Variant::Antichess).to_fen(), fen);
+
+    for variant in [Variant::Antichess, Variant::RacingKings] {
+        assert!(matches!(
+            Position::from_variant_fen(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                variant
+            ),
+            Err(FenError::BadCastling(_))
+        ));
+    }
+}
+
+#[test]
+fn crazyhouse_captures_fill_the_pocket() {
+    let mut position = Variant::Crazyhouse.start();
+    play(&mut position, &["e2e4", "d7d5", "e4d5", "d8d5", "b1c3"]);
+    assert_eq!(position.pocket(0, Piece::Pawn), 1);
+    assert_eq!(position.pocket(1, Piece::Pawn), 1);
+    assert_eq!(
+        position.to_fen(),
+        "rnb1kbnr/ppp1pppp/8/3q4/8/2N5/PPPP1PPP/R1BQKBNR[Pp] b KQkq - 1 3"
+    );
+
+    // A promoted piece goes back to being a pawn
+    let mut position = variant_fen("4k3/1Q~6/8/8/8/8/K7/1r6[] b - - 0 1", Variant::Crazyhouse);
+    play(&mut position, &["b1b7"]);
+    assert_eq!(position.pocket(1, Piece::Pawn), 1);
+    assert_eq!(position.pocket(1, Piece::Queen), 0);
+    assert!(!position.promoted[1][1]);
+}
+
+#[test]
+fn crazyhouse_drops() {
+    let position = variant_fen(
+        "rnbqkbnr/ppp1pppp/8/8/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3",
+        Variant::Crazyhouse,
+    );
+    let drops = position.drops(Piece::Pawn);
+    // Every empty square off the first and last rank
+    assert_eq!(drops.len(), 34);
+    assert!(drops.iter().all(|m| m.to[1] != 0 && m.to[1] != 7));
+    assert!(position.drops(Piece::Knight).is_empty());
+
+    let m = position.parse_uci_move("P@d6").unwrap();
+    assert!(m.drop);
+    assert_eq!(m.to_uci(), "P@d6");
+    assert_eq!(position.san(&m), "P@d6");
+    assert_eq!(position.parse_san("@d6"), Ok(m));
+    assert!(position.parse_uci_move("P@d8").is_none());
+
+    let mut after = position.clone();
+    after.make_move(&m);
+    assert_eq!(after.pocket(0, Piece::Pawn), 0);
+    assert_eq!(
+        after.to_fen(),
+        "rnbqkbnr/ppp1pppp/3P4/8/8/8/PPPP1PPP/RNBQKBNR[p] b KQkq - 1 3"
+    );
+
+    // In check, only drops that block it are allowed
+    let position = variant_fen("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1", Variant::Crazyhouse);
+    let squares: Vec<String> = position
+        .drops(Piece::Knight)
+        .iter()
+        .map(|m| m.to_uci())
+        .collect();
+    assert_eq!(squares, ["N@b1", "N@c1", "N@d1"]);
+}
+
+#[test]
+fn crazyhouse_games_round_trip() {
+    let mut record = GameRecord::new(Variant::Crazyhouse.start());
+    for uci in [
+        "e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a5", "P@d4", "P@e4",
+    ] {
+        let m = record.position().parse_uci_move(uci).unwrap();
+        record.push(m);
+    }
+    let pgn = record.to_pgn();
+    assert!(pgn.contains("[Variant \"Crazyhouse\"]\n"));
+    assert!(pgn.contains("4. P@d4 P@e4 *"));
+
+    let games = parse_games(&pgn).unwrap();
+    assert_eq!(games[0].moves(), record.moves());
+}